@@ -1,13 +1,107 @@
+use std::{
+    collections::VecDeque, f32::consts::TAU, fmt::Display, ops::RangeInclusive, time::{Duration, SystemTime}
+};
+
 use bevy::{
-    math::bounding::{Aabb2d, BoundingCircle, BoundingVolume, IntersectsVolume}, prelude::*, sprite::{MaterialMesh2dBundle, Mesh2dHandle}, window::close_on_esc
+    math::bounding::{Aabb2d, BoundingCircle, BoundingVolume, IntersectsVolume}, prelude::*, sprite::{MaterialMesh2dBundle, Mesh2dHandle}, window::{close_on_esc, PrimaryWindow}
 };
 use iyes_perf_ui::{PerfUiCompleteBundle, PerfUiPlugin};
+use serde::Deserialize;
+
+use precision::{delta_seconds, to_phys, to_phys_scalar, to_render, PhysVec};
+
+// Core physics (velocity and position integration) runs in f64 with the
+// `high_precision` feature so long matches don't drift. Bevy's bounding volumes
+// are f32 only, so collision checks and rendering use the converted values.
+#[cfg(not(feature = "high_precision"))]
+mod precision {
+    use bevy::prelude::*;
+
+    pub type PhysVec = Vec2;
+
+    pub fn delta_seconds(time: &Time) -> f32 {
+        time.delta_seconds()
+    }
+
+    pub fn to_phys(v: Vec2) -> PhysVec {
+        v
+    }
+
+    pub fn to_phys_scalar(v: f32) -> f32 {
+        v
+    }
+
+    pub fn to_render(v: PhysVec) -> Vec2 {
+        v
+    }
+}
+
+#[cfg(feature = "high_precision")]
+mod precision {
+    use bevy::{math::DVec2, prelude::*};
+
+    pub type PhysVec = DVec2;
+
+    pub fn delta_seconds(time: &Time) -> f64 {
+        time.delta_seconds_f64()
+    }
+
+    pub fn to_phys(v: Vec2) -> PhysVec {
+        v.as_dvec2()
+    }
+
+    pub fn to_phys_scalar(v: f32) -> f64 {
+        v as f64
+    }
+
+    pub fn to_render(v: PhysVec) -> Vec2 {
+        v.as_vec2()
+    }
+}
+
+// Render layers, higher z is drawn on top
+const GRID_Z: f32 = -1.0;
+const BALL_SHADOW_Z: f32 = -0.5;
+const WALL_Z: f32 = 0.0;
+const PADDLE_GHOST_Z: f32 = 0.9;
+const PADDLE_Z: f32 = 1.0;
+const BALL_Z: f32 = 2.0;
+// Used instead of `BALL_Z` when the ball is set to pass behind the paddles
+const BALL_BEHIND_PADDLES_Z: f32 = 0.5;
+
+// Config
+const CONFIG_PATH: &str = "config.toml";
+const CONFIG_POLL_INTERVAL: f32 = 0.5;
+// Reload only once the file has gone this long without changing. Kept longer
+// than the poll interval so a write seen by the next poll restarts the wait.
+const CONFIG_RELOAD_DEBOUNCE: f32 = 1.0;
 
 // Ball
-const BALL_INITIAL_POSITION:  Vec3 = Vec3::new(200.0, 0.0, 1.0);
+const BALL_INITIAL_POSITION:  Vec2 = Vec2::new(200.0, 0.0);
 const BALL_INITIAL_DIRECTION: Vec2 = Vec2::new(0.5, 0.0);
 const BALL_SPEED: f32 = 400.0;
 const BALL_DIAMETER: f32 = 25.0;
+const BALL_COLOR: Color = Color::ORANGE_RED;
+// Fastest the ball may go, boosts included
+const MAX_BALL_SPEED: f32 = 900.0;
+// Ball shadow
+const BALL_SHADOW_TOGGLE_KEY: KeyCode = KeyCode::F8;
+const BALL_SHADOW_OFFSET: Vec2 = Vec2::new(6.0, -8.0);
+const BALL_SHADOW_OPACITY: f32 = 0.35;
+
+// Ball wobble
+const BALL_WOBBLE_TOGGLE_KEY: KeyCode = KeyCode::F6;
+const BALL_WOBBLE_AMPLITUDE: f32 = 0.08;
+// The ball keeps a steady scale below the first speed and wobbles fully at the second
+const BALL_WOBBLE_MIN_SPEED: f32 = 300.0;
+const BALL_WOBBLE_MAX_SPEED: f32 = 600.0;
+// Oscillation in radians per pixel travelled, so faster balls wobble faster
+const BALL_WOBBLE_FREQUENCY: f32 = 0.03;
+// Serves flatter than this (in degrees) are nudged up to it
+const MIN_SERVE_ANGLE: f32 = 10.0;
+const CONSISTENT_SERVE_TOGGLE_KEY: KeyCode = KeyCode::F11;
+// How long the ball ignores the collider it just bounced off
+const BALL_COLLISION_DEBOUNCE: f32 = 0.05;
 
 // Window
 const WINDOW_TITLE: &str = "Pong with your friend";
@@ -16,29 +110,104 @@ const WINDOW_H: f32 = 800.0;
 
 // Walls
 const WALL_THICKNESS: f32 = 10.0;
+const WALL_COLOR: Color = Color::BISQUE;
+const WALL_GLOW_COLOR: Color = Color::WHITE;
+const WALL_GLOW_TOGGLE_KEY: KeyCode = KeyCode::F4;
+const WALL_GLOW_INTENSITY: f32 = 0.6;
 
 const ARENA_W: f32 = 1200.0;
 const ARENA_H: f32 = 800.0;
 
-const TOP_WALL:    f32 = ARENA_H / 2.0;
-const BOTTOM_WALL: f32 = -(ARENA_H / 2.0);
-const LEFT_WALL:   f32 = -(ARENA_W / 2.0);
-const RIGHT_WALL:  f32 = ARENA_W / 2.0;
-
 // Paddle
 const PADDLE_W: f32 = 20.0;
 const PADDLE_H: f32 = 200.0;
 const PADDLE_DISTANCE_TO_WALL: f32 = 20.0;
 const PADDLE_SPEED: f32 = 300.0;
-const PADDLE_ONE_INITIAL_POSITION: f32 = LEFT_WALL + WALL_THICKNESS + PADDLE_DISTANCE_TO_WALL + PADDLE_W / 2.0;
+const PADDLE_COLOR: Color = Color::ALICE_BLUE;
+// Holding a precision key moves that player's paddle at this fraction of its speed
+const LEFT_PRECISION_KEY: KeyCode = KeyCode::ShiftLeft;
+const RIGHT_PRECISION_KEY: KeyCode = KeyCode::ShiftRight;
+const PADDLE_PRECISION_FACTOR: f32 = 0.35;
+// Angle off the horizontal a ball leaves the paddle with when it hits right at the edge
+const PADDLE_MAX_BOUNCE_ANGLE: f32 = 60.0;
+// After hitting the ball a paddle won't reflect that same ball again for this long
+const PADDLE_HIT_COOLDOWN: f32 = 0.15;
+
+// Ball boost
+const LEFT_BOOST_KEY: KeyCode = KeyCode::ControlLeft;
+const RIGHT_BOOST_KEY: KeyCode = KeyCode::ControlRight;
+// The ball speed is multiplied by this when a player boosts it, up to `MAX_BALL_SPEED`
+const BALL_BOOST_FACTOR: f32 = 1.5;
+
+// Paddle ghosting
+const PADDLE_GHOST_TOGGLE_KEY: KeyCode = KeyCode::F5;
+const PADDLE_GHOST_SPEED_THRESHOLD: f32 = 250.0;
+const PADDLE_GHOST_MAX: usize = 6;
+const PADDLE_GHOST_INTERVAL: f32 = 0.03;
+const PADDLE_GHOST_LIFETIME: f32 = 0.15;
+const PADDLE_GHOST_ALPHA: f32 = 0.35;
+
+// AI opponent
+// Fraction of the paddle speed the AI moves at, so it can be outrun
+const AI_SPEED_FACTOR: f32 = 0.85;
+// Largest distance, in px, the AI aims off from where the ball will actually be
+const AI_TRACKING_ERROR: f32 = 40.0;
+const AI_ERROR_FREQUENCY: f32 = 0.7;
+
+// Assist
+const ASSIST_TOGGLE_KEY: KeyCode = KeyCode::F3;
+// Fraction of the paddle speed used when nudging the paddle toward the ball
+const ASSIST_STRENGTH: f32 = 0.35;
+
+// Grid
+const GRID_TOGGLE_KEY: KeyCode = KeyCode::F7;
+const GRID_SPACING: f32 = 50.0;
+const GRID_LINE_THICKNESS: f32 = 1.0;
+const GRID_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.06);
+
+// Bullet time
+// How close (beyond touching) the ball must pass a paddle to count as a near miss
+const NEAR_MISS_MARGIN: f32 = 15.0;
+const BULLET_TIME_SCALE: f32 = 0.3;
+// Measured in real time, so it isn't stretched by the slowdown itself
+const BULLET_TIME_DURATION: f32 = 0.6;
+
+// Scoring
+// Points needed to win a match, after which the score starts over
+const WIN_SCORE: u32 = 11;
+// Flavor conversion for reported ball speeds, 400 px/s being 40 mph
+const MPH_PER_PX_PER_SECOND: f32 = 0.1;
+
+// Score hit-stop
+const SCORE_HIT_STOP_TOGGLE_KEY: KeyCode = KeyCode::F9;
+// How long everything freezes when a point is scored, in real time
+const SCORE_HIT_STOP_DURATION: f32 = 0.08;
+
+// Comeback assist
+const COMEBACK_ASSIST_TOGGLE_KEY: KeyCode = KeyCode::F10;
+// Fraction of the ball speed taken off per point the receiving player is behind
+const COMEBACK_SLOWDOWN_PER_POINT: f32 = 0.03;
+const COMEBACK_MAX_SLOWDOWN: f32 = 0.15;
+
+// Frame graph
+const FRAME_GRAPH_TOGGLE_KEY: KeyCode = KeyCode::F2;
+const FRAME_GRAPH_CAPACITY: usize = 120;
+const FRAME_GRAPH_BAR_W: f32 = 2.0;
+const FRAME_GRAPH_H: f32 = 60.0;
+const FRAME_GRAPH_MARGIN: f32 = 20.0;
+// A frame this long (or longer) fills the whole graph height
+const FRAME_GRAPH_MAX_DELTA: f32 = 1.0 / 30.0;
+const FRAME_GRAPH_TARGET_DELTA: f32 = 1.0 / 60.0;
 
 fn main() {
+    let orientation = Orientation::from_args();
+
     App::new()
         .add_plugins(DefaultPlugins.set(
             WindowPlugin {
                 primary_window: Some(Window {
                     title: WINDOW_TITLE.into(),
-                    resolution: (WINDOW_W, WINDOW_H).into(),
+                    resolution: orientation.window_resolution().into(),
                     resizable: false,
                     ..default()
                 }),
@@ -46,257 +215,3006 @@ fn main() {
             }
         ))
         .add_plugins(PerfUiPlugin)
+        .insert_resource(orientation)
+        .insert_resource(GameMode::from_args())
         .add_systems(Update, close_on_esc)
+        .init_resource::<FrameGraph>()
+        .add_systems(
+            Update, (
+                record_frame_time,
+                toggle_frame_graph,
+                draw_frame_graph,
+            ).chain()
+        )
         .add_event::<CollisionEvent>()
+        .init_resource::<Score>()
+        .init_resource::<RallyStats>()
+        .init_resource::<MaxRallyDuration>()
+        .add_systems(
+            Update, (
+                update_rally_clock_text,
+                update_boost_indicators,
+                update_serve_zone_prompt,
+                update_click_to_serve_prompt,
+            )
+        )
+        .init_resource::<MinServeAngle>()
+        .init_resource::<PendingServe>()
+        .init_resource::<ConsistentServe>()
+        .add_systems(Update, toggle_consistent_serve)
+        .init_resource::<AssistMode>()
+        .init_resource::<AssistStrength>()
+        .add_systems(Update, (toggle_assist, update_assist_indicator).chain())
+        .init_resource::<WallGlow>()
+        .add_systems(Update, (toggle_wall_glow, update_wall_glow).chain())
+        .init_resource::<TimeScale>()
+        .init_resource::<BulletTime>()
+        .init_resource::<ComebackAssist>()
+        .add_systems(Update, toggle_comeback_assist)
+        .init_resource::<ScoreHitStop>()
+        .init_resource::<HitStop>()
+        .add_systems(
+            Update, (
+                toggle_score_hit_stop,
+                update_bullet_time,
+                update_hit_stop,
+                apply_time_scale,
+            ).chain()
+        )
+        .init_resource::<PaddleGhosting>()
+        .init_resource::<GhostSpeedThreshold>()
+        .add_systems(Update, (toggle_paddle_ghosting, fade_paddle_ghosts))
+        .init_resource::<BallWobble>()
+        .add_systems(Update, (toggle_ball_wobble, wobble_ball).chain())
+        .add_systems(PreStartup, load_config)
         .add_systems(Startup, setup)
+        .add_systems(Startup, spawn_opponent.after(setup))
+        .add_systems(Update, play_collision_sound)
+        .init_resource::<ShowGrid>()
+        .init_resource::<GridStyle>()
+        .add_systems(Startup, spawn_grid)
+        .init_resource::<BallShadow>()
+        .init_resource::<BallShadowStyle>()
+        .add_systems(Startup, spawn_ball_shadow.after(setup))
+        .add_systems(Update, (toggle_ball_shadow, update_ball_shadow).chain())
+        .add_systems(Update, (toggle_grid, update_grid_visibility).chain())
+        .add_systems(Update, (watch_config, apply_config).chain())
         .add_systems(
             FixedUpdate, (
                 apply_velocity,
                 move_paddle,
+                boost_ball,
+                move_opponent,
+                spawn_paddle_ghosts,
                 check_for_collision,
+                tick_rally_clock,
+                check_for_score,
+                serve_ball,
+                apply_comeback_assist,
+                track_rally_speed,
+                wrap_through_wall_gaps,
             ).chain() // chaining systems together runs them in order
         )
         .run();
 }
 
 #[derive(Component, Deref, DerefMut)]
-struct Velocity(Vec2);
+struct Velocity(PhysVec);
 
-#[derive(Component)]
-struct Paddle;
+/// Authoritative position for entities integrated by `apply_velocity`, kept at
+/// physics precision. The `Transform` is only a rendered copy of it, so anything
+/// teleporting the entity must update this too.
+#[derive(Component, Deref, DerefMut)]
+struct Position(PhysVec);
 
 #[derive(Component)]
-struct Ball;
+struct Paddle;
 
+/// Drives a paddle toward where the ball is headed instead of reading the keyboard
 #[derive(Component)]
-struct Collider;
-
-#[derive(Event, Default)]
-struct CollisionEvent;
+struct OpponentController {
+    /// Fraction of the paddle speed the AI may move at
+    speed_factor: f32,
+    /// How far off, in px, the AI may aim from the predicted ball position
+    tracking_error: f32,
+}
 
-// This bundle is a collection of the components that define a "wall" in our game
-#[derive(Bundle)]
-struct WallBundle {
-    // You can nest bundles inside of other bundles like this
-    // Allowing you to compose their functionality
-    sprite_bundle: SpriteBundle,
-    collider: Collider,
+impl Default for OpponentController {
+    fn default() -> Self {
+        OpponentController {
+            speed_factor: AI_SPEED_FACTOR,
+            tracking_error: AI_TRACKING_ERROR,
+        }
+    }
 }
 
-/// Which side of the arena is this wall located on?
-enum WallLocation {
+/// Which side of the arena a paddle defends, and so which keys move it
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum Player {
     Left,
     Right,
-    Bottom,
-    Top,
 }
 
-impl WallLocation {
-    fn position(&self) -> Vec2 {
+impl Player {
+    /// Keys moving the paddle toward +y and -y in arena space, respectively
+    fn paddle_keys(&self, orientation: Orientation) -> (KeyCode, KeyCode) {
+        match (self, orientation) {
+            (Player::Left, Orientation::Horizontal) => (KeyCode::KeyW, KeyCode::KeyS),
+            (Player::Right, Orientation::Horizontal) => (KeyCode::ArrowUp, KeyCode::ArrowDown),
+            // arena +y points to the right of the screen once rotated
+            (Player::Left, Orientation::Vertical) => (KeyCode::KeyD, KeyCode::KeyA),
+            (Player::Right, Orientation::Vertical) => (KeyCode::ArrowRight, KeyCode::ArrowLeft),
+        }
+    }
+
+    fn precision_key(&self) -> KeyCode {
         match self {
-            WallLocation::Left => Vec2::new(LEFT_WALL, 0.),
-            WallLocation::Right => Vec2::new(RIGHT_WALL, 0.),
-            WallLocation::Bottom => Vec2::new(0., BOTTOM_WALL),
-            WallLocation::Top => Vec2::new(0., TOP_WALL),
+            Player::Left => LEFT_PRECISION_KEY,
+            Player::Right => RIGHT_PRECISION_KEY,
         }
     }
 
-    fn size(&self) -> Vec2 {
+    fn boost_key(&self) -> KeyCode {
         match self {
-            WallLocation::Left | WallLocation::Right => {
-                Vec2::new(WALL_THICKNESS, ARENA_H + WALL_THICKNESS)
-            }
-            WallLocation::Bottom | WallLocation::Top => {
-                Vec2::new(ARENA_W + WALL_THICKNESS, WALL_THICKNESS)
-            }
+            Player::Left => LEFT_BOOST_KEY,
+            Player::Right => RIGHT_BOOST_KEY,
         }
     }
-}
 
-impl WallBundle {
-    // This "builder method" allows us to reuse logic across our wall entities,
-    // making our code easier to read and less prone to bugs when we change the logic
-    fn new(location: WallLocation) -> WallBundle {
-        WallBundle {
-            sprite_bundle: SpriteBundle {
-                transform: Transform {
-                    // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
-                    // This is used to determine the order of our sprites
-                    translation: location.position().extend(0.0),
-                    // The z-scale of 2D objects must always be 1.0,
-                    // or their ordering will be affected in surprising ways.
-                    // See https://github.com/bevyengine/bevy/issues/4149
-                    scale: location.size().extend(1.0),
-                    ..default()
-                },
-                sprite: Sprite {
-                    color: Color::BISQUE,
-                    ..default()
-                },
-                ..default()
-            },
-            collider: Collider,
+    /// The player whose serve heads toward `toward_x`
+    fn serving_toward(toward_x: f32) -> Player {
+        if toward_x > 0.0 {
+            Player::Left
+        } else {
+            Player::Right
         }
     }
 }
 
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>
-) {
-    commands.spawn(Camera2dBundle::default());
+#[derive(Component)]
+struct Ball;
 
-    commands.spawn(PerfUiCompleteBundle::default());
+/// Whether a paddle's player still has this rally's ball boost to use
+#[derive(Component)]
+struct BoostCharge(bool);
 
-    // Spawn Ball
-    commands.spawn((
-        MaterialMesh2dBundle {
-            mesh: Mesh2dHandle(meshes.add(Circle { radius: BALL_DIAMETER })),
-            material: materials.add(Color::ORANGE_RED),
-            transform: Transform::from_translation(BALL_INITIAL_POSITION),
-            ..default()
-        },
-        Ball,
-        Velocity(BALL_INITIAL_DIRECTION.normalize() * BALL_SPEED),
-    ));
-    // Paddles
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform {
-                // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
-                // This is used to determine the order of our sprites
-                translation: Vec3::new(PADDLE_ONE_INITIAL_POSITION, 0., 1.),
-                // The z-scale of 2D objects must always be 1.0,
-                // or their ordering will be affected in surprising ways.
-                // See https://github.com/bevyengine/bevy/issues/4149
-                scale: Vec3::new(PADDLE_W, PADDLE_H, 1.),
-                ..default()
-            },
-            sprite: Sprite {
-                color: Color::ALICE_BLUE,
-                ..default()
-            },
-            ..default()
-        },
-        Paddle,
-        Collider,
-        Velocity(Vec2::new(0.0, 0.0))
-    ));
+/// "Boost ready" label of the given player
+#[derive(Component)]
+struct BoostIndicator(Player);
 
-    // Spawn Walls
-    commands.spawn(WallBundle::new(WallLocation::Top));
-    commands.spawn(WallBundle::new(WallLocation::Bottom));
-    commands.spawn(WallBundle::new(WallLocation::Left));
-    commands.spawn(WallBundle::new(WallLocation::Right));
+/// The ball a paddle last hit, which it won't reflect again until the timer
+/// finishes, so a paddle moving into the ball can't carry it along
+#[derive(Component)]
+struct HitCooldown {
+    ball: Option<Entity>,
+    timer: Timer,
 }
 
-fn apply_velocity(
-    mut query: Query<(&mut Transform, &Velocity)>,
-    time: Res<Time>
-) {
-    for (mut transform, velocity) in &mut query {
-        transform.translation.x += velocity.x * time.delta_seconds();
-        transform.translation.y += velocity.y * time.delta_seconds();
+impl Default for HitCooldown {
+    fn default() -> Self {
+        HitCooldown {
+            ball: None,
+            timer: Timer::from_seconds(PADDLE_HIT_COOLDOWN, TimerMode::Once),
+        }
     }
 }
 
-fn move_paddle(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut query: Query<&mut Transform, With<Paddle>>,
-    time: Res<Time>
-) {
-    let mut paddle_transform = query.single_mut();
-    let mut direction = 0.0;
+/// Leaves faint afterimages behind a paddle while it moves fast
+#[derive(Component)]
+struct GhostEmitter {
+    last_position: Vec3,
+    timer: Timer,
+}
 
-    if keyboard_input.pressed(KeyCode::ArrowUp) {
-        direction += 1.0;
+impl GhostEmitter {
+    fn new(position: Vec3) -> GhostEmitter {
+        GhostEmitter {
+            last_position: position,
+            timer: Timer::from_seconds(PADDLE_GHOST_INTERVAL, TimerMode::Repeating),
+        }
     }
+}
 
-    if keyboard_input.pressed(KeyCode::ArrowDown) {
-        direction -= 1.0;
+/// A fading afterimage of a paddle, despawned when the timer finishes
+#[derive(Component, Deref, DerefMut)]
+struct PaddleGhost(Timer);
+
+#[derive(Component)]
+struct Collider;
+
+/// The collider the ball last bounced off, ignored until the timer finishes.
+/// Keeps a slow ball from reflecting off the same paddle on consecutive frames.
+#[derive(Component)]
+struct LastCollider(Option<Entity>, Timer);
+
+impl Default for LastCollider {
+    fn default() -> Self {
+        LastCollider(None, Timer::from_seconds(BALL_COLLISION_DEBOUNCE, TimerMode::Once))
     }
+}
 
-    let new_paddle_position = 
-        paddle_transform.translation.y + direction * time.delta_seconds() * PADDLE_SPEED;
+/// Sent whenever the ball bounces off something, saying what it hit
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+enum CollisionEvent {
+    Paddle,
+    Wall,
+}
 
-    let top_bound = TOP_WALL - WALL_THICKNESS - PADDLE_H / 2. - PADDLE_DISTANCE_TO_WALL;
-    let bottom_bound = BOTTOM_WALL + WALL_THICKNESS + PADDLE_H / 2. + PADDLE_DISTANCE_TO_WALL;
+/// Bounce sounds, loaded once in `setup`
+#[derive(Resource)]
+struct CollisionSound {
+    paddle: Handle<AudioSource>,
+    wall: Handle<AudioSource>,
+}
 
-    paddle_transform.translation.y = new_paddle_position.clamp(bottom_bound, top_bound);
+#[derive(Resource, Default, Debug)]
+struct Score {
+    left: u32,
+    right: u32,
 }
 
-fn check_for_collision(
-    mut ball_query: Query<(&mut Velocity, &Transform), With<Ball>>,
-    collider_query: Query<(Entity, &Transform), With<Collider>>,
-    mut collision_events: EventWriter<CollisionEvent>,
-) {
-    let (mut ball_velocity, ball_transform) = ball_query.single_mut();
+/// Fastest the ball went since the last serve, in px/s, reported when the
+/// point ends
+#[derive(Resource, Default, Debug)]
+struct RallyStats {
+    peak_speed: f32,
+}
 
-    // check collision with Walls
-    for (collider_entity, transform) in &collider_query {
-        let collision = collide_with_side(
-            // `BALL_DIAMETER * 0.8` makes the ball overlap 20% before considering a
-            // collision, this makes the "bounce" feel more natural
-            BoundingCircle::new(ball_transform.translation.truncate(), BALL_DIAMETER * 0.8),
-            Aabb2d::new(
-                transform.translation.truncate(),
-                transform.scale.truncate() / 2.
-            )
-        );
+/// Time the current rally has been in play, for capping it at
+/// `GameConfig::max_rally_duration`
+#[derive(Resource, Default)]
+struct MaxRallyDuration(Timer);
 
+#[derive(Component)]
+struct RallyClockText;
 
-        if let Some(collision) = collision {
-            collision_events.send_default();
+/// Asks the serving player to bring their paddle back into the serve zone
+#[derive(Component)]
+struct ServeZonePrompt;
 
-            let mut reflect_x = false;
-            let mut reflect_y = false;
+/// Shown while a serve waits for the assisted player's click
+#[derive(Component)]
+struct ClickToServePrompt;
 
-            match collision {
-                Collision::Top => reflect_y = ball_velocity.y < 0.0,
-                Collision::Bottom => reflect_y = ball_velocity.y > 0.0,
-                Collision::Left => reflect_x = ball_velocity.x > 0.0,
-                Collision::Right => reflect_x = ball_velocity.x < 0.0,
-            }
+/// x positions the ball's center has to pass to score on each side. Moving
+/// them behind the walls makes the ball visibly enter the goal first.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+struct GoalLine {
+    left: f32,
+    right: f32,
+}
 
-            // TODO: collision on x-axis scores a point to opposite side and starts a new round
-            if reflect_x {
-                ball_velocity.x = -ball_velocity.x;
+impl GoalLine {
+    fn at_walls(config: &GameConfig) -> GoalLine {
+        GoalLine {
+            left: config.left_wall(),
+            right: config.right_wall(),
+        }
+    }
+}
+
+impl Default for GoalLine {
+    fn default() -> Self {
+        GoalLine::at_walls(&GameConfig::default())
+    }
+}
+
+/// Gameplay values read from `CONFIG_PATH` at startup. Anything missing from
+/// the file, or the whole file, falls back to the constants above.
+#[derive(Resource, Clone, Debug, PartialEq)]
+struct GameConfig {
+    ball_speed: f32,
+    paddle_speed: f32,
+    paddle_w: f32,
+    paddle_h: f32,
+    arena_w: f32,
+    arena_h: f32,
+    wall_thickness: f32,
+    win_score: u32,
+    ball_color: Color,
+    paddle_color: Color,
+    wall_color: Color,
+    /// Draws the ball under the paddles instead of over them
+    ball_behind_paddles: bool,
+    paddle_shape: PaddleShape,
+    wall_gaps: WallGaps,
+    /// Gives each player one ball boost per rally
+    ball_boost: bool,
+    /// Unit the rally's top speed is reported in
+    speed_unit: SpeedUnit,
+    /// Seconds a rally may last before `rally_timeout` decides it, 0 for no limit
+    max_rally_duration: f32,
+    rally_timeout: RallyTimeout,
+    /// Height of the band around the arena's middle the serving paddle has to
+    /// be in for the serve to go, 0 to serve from anywhere
+    serve_zone: f32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            ball_speed: BALL_SPEED,
+            paddle_speed: PADDLE_SPEED,
+            paddle_w: PADDLE_W,
+            paddle_h: PADDLE_H,
+            arena_w: ARENA_W,
+            arena_h: ARENA_H,
+            wall_thickness: WALL_THICKNESS,
+            win_score: WIN_SCORE,
+            ball_color: BALL_COLOR,
+            paddle_color: PADDLE_COLOR,
+            wall_color: WALL_COLOR,
+            ball_behind_paddles: false,
+            paddle_shape: PaddleShape::Box,
+            wall_gaps: WallGaps::default(),
+            ball_boost: false,
+            speed_unit: SpeedUnit::Px,
+            max_rally_duration: 0.0,
+            rally_timeout: RallyTimeout::Reserve,
+            serve_zone: 0.0,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Reads the config file, logging (rather than failing on) anything wrong with it
+    fn load(path: &str) -> GameConfig {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("{path} not found, using default config");
+                return GameConfig::default();
+            }
+            Err(err) => {
+                warn!("could not read {path}: {err}, using default config");
+                return GameConfig::default();
             }
+        };
 
-            if reflect_y {
-                ball_velocity.y = -ball_velocity.y;
+        match toml::from_str::<GameConfigFile>(&contents) {
+            Ok(file) => file.into_config(),
+            Err(err) => {
+                warn!("could not parse {path}: {err}, using default config");
+                GameConfig::default()
             }
         }
     }
+
+    fn top_wall(&self) -> f32 {
+        self.arena_h / 2.
+    }
+
+    fn bottom_wall(&self) -> f32 {
+        -(self.arena_h / 2.)
+    }
+
+    fn left_wall(&self) -> f32 {
+        -(self.arena_w / 2.)
+    }
+
+    fn right_wall(&self) -> f32 {
+        self.arena_w / 2.
+    }
+
+    /// Whether going from `self` to `other` needs entities re-spawned, which
+    /// only happens at startup
+    fn is_structural_change(&self, other: &GameConfig) -> bool {
+        self.arena_w != other.arena_w
+            || self.arena_h != other.arena_h
+            || self.wall_thickness != other.wall_thickness
+            || self.wall_gaps != other.wall_gaps
+    }
+
+    fn ball_z(&self) -> f32 {
+        if self.ball_behind_paddles {
+            BALL_BEHIND_PADDLES_Z
+        } else {
+            BALL_Z
+        }
+    }
+
+    /// x position of a player's paddle
+    fn paddle_x(&self, player: Player) -> f32 {
+        let distance_to_center = self.wall_thickness + PADDLE_DISTANCE_TO_WALL + self.paddle_w / 2.;
+
+        match player {
+            Player::Left => self.left_wall() + distance_to_center,
+            Player::Right => self.right_wall() - distance_to_center,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-enum Collision {
-    Left,
-    Right,
-    Top,
-    Bottom
+/// Openings in the top and bottom walls, as `[start, end]` x ranges. A ball
+/// leaving through one wraps around to the opposite side of the arena.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+struct WallGaps {
+    top: Vec<[f32; 2]>,
+    bottom: Vec<[f32; 2]>,
 }
 
-fn collide_with_side(ball: BoundingCircle, wall: Aabb2d,) -> Option<Collision> {
-    if !ball.intersects(&wall) {
-        return None;
+/// x ranges of the pieces left of a top/bottom wall spanning
+/// `-half_width..half_width` once `gaps` are cut out of it
+fn wall_segments(gaps: &[[f32; 2]], half_width: f32) -> Vec<(f32, f32)> {
+    let mut gaps: Vec<(f32, f32)> = gaps
+        .iter()
+        .map(|[a, b]| (a.min(*b).max(-half_width), a.max(*b).min(half_width)))
+        .filter(|(start, end)| start < end)
+        .collect();
+    gaps.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut segments = Vec::new();
+    let mut start = -half_width;
+    for (gap_start, gap_end) in gaps {
+        if gap_start > start {
+            segments.push((start, gap_start));
+        }
+        start = start.max(gap_end);
+    }
+    if start < half_width {
+        segments.push((start, half_width));
     }
 
-    let closest = wall.closest_point(ball.center());
-    let offset = ball.center() - closest;
-    let side = if offset.x.abs() > offset.y.abs() {
-        if offset.x > 0. {
-            Collision::Right
-        } else {
-            Collision::Left
+    segments
+}
+
+/// Polls the config file's modification time so edits are applied while the game runs
+#[derive(Resource)]
+struct ConfigWatcher {
+    poll_timer: Timer,
+    last_modified: Option<SystemTime>,
+    /// Restarted on every detected write, the reload happens once it finishes
+    debounce: Option<Timer>,
+}
+
+impl ConfigWatcher {
+    fn new(path: &str) -> ConfigWatcher {
+        ConfigWatcher {
+            poll_timer: Timer::from_seconds(CONFIG_POLL_INTERVAL, TimerMode::Repeating),
+            last_modified: modified_time(path),
+            debounce: None,
         }
-    } else if offset.y > 0.0 {
-        Collision::Top
-    } else {
-        Collision::Bottom
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The config file as written on disk, where every field is optional
+#[derive(Deserialize, Default)]
+struct GameConfigFile {
+    ball_speed: Option<f32>,
+    paddle_speed: Option<f32>,
+    paddle_w: Option<f32>,
+    paddle_h: Option<f32>,
+    arena_w: Option<f32>,
+    arena_h: Option<f32>,
+    wall_thickness: Option<f32>,
+    win_score: Option<u32>,
+    /// Colors are hex strings, e.g. `"#FF4500"`
+    ball_color: Option<String>,
+    paddle_color: Option<String>,
+    wall_color: Option<String>,
+    ball_behind_paddles: Option<bool>,
+    paddle_shape: Option<PaddleShape>,
+    wall_gaps: Option<WallGaps>,
+    ball_boost: Option<bool>,
+    speed_unit: Option<SpeedUnit>,
+    max_rally_duration: Option<f32>,
+    rally_timeout: Option<RallyTimeout>,
+    serve_zone: Option<f32>,
+}
+
+impl GameConfigFile {
+    fn into_config(self) -> GameConfig {
+        let default = GameConfig::default();
+
+        let mut config = GameConfig {
+            ball_speed: checked("ball_speed", self.ball_speed, 50.0..=2000.0, default.ball_speed),
+            paddle_speed: checked("paddle_speed", self.paddle_speed, 50.0..=2000.0, default.paddle_speed),
+            paddle_w: checked("paddle_w", self.paddle_w, 5.0..=100.0, default.paddle_w),
+            paddle_h: checked("paddle_h", self.paddle_h, 20.0..=600.0, default.paddle_h),
+            arena_w: checked("arena_w", self.arena_w, 400.0..=WINDOW_W, default.arena_w),
+            arena_h: checked("arena_h", self.arena_h, 300.0..=WINDOW_H, default.arena_h),
+            wall_thickness: checked("wall_thickness", self.wall_thickness, 2.0..=50.0, default.wall_thickness),
+            win_score: checked("win_score", self.win_score, 1..=99, default.win_score),
+            ball_color: parse_color("ball_color", self.ball_color, default.ball_color),
+            paddle_color: parse_color("paddle_color", self.paddle_color, default.paddle_color),
+            wall_color: parse_color("wall_color", self.wall_color, default.wall_color),
+            ball_behind_paddles: self.ball_behind_paddles.unwrap_or(default.ball_behind_paddles),
+            paddle_shape: self.paddle_shape.unwrap_or(default.paddle_shape),
+            wall_gaps: self.wall_gaps.unwrap_or(default.wall_gaps),
+            ball_boost: self.ball_boost.unwrap_or(default.ball_boost),
+            speed_unit: self.speed_unit.unwrap_or(default.speed_unit),
+            max_rally_duration: checked(
+                "max_rally_duration",
+                self.max_rally_duration,
+                0.0..=600.0,
+                default.max_rally_duration
+            ),
+            rally_timeout: self.rally_timeout.unwrap_or(default.rally_timeout),
+            serve_zone: checked("serve_zone", self.serve_zone, 0.0..=WINDOW_H, default.serve_zone),
+        };
+
+        // the paddle has to fit between the top and bottom walls
+        let max_paddle_h = config.arena_h - 2. * (config.wall_thickness + PADDLE_DISTANCE_TO_WALL);
+        if config.paddle_h > max_paddle_h {
+            let paddle_h = default.paddle_h.min(max_paddle_h);
+            warn!("paddle_h = {} does not fit in the arena, using {paddle_h}", config.paddle_h);
+            config.paddle_h = paddle_h;
+        }
+
+        config
+    }
+}
+
+/// Returns `value` if it lies in `range`, otherwise warns and falls back to `default`
+fn checked<T: PartialOrd + Display>(name: &str, value: Option<T>, range: RangeInclusive<T>, default: T) -> T {
+    match value {
+        Some(value) if range.contains(&value) => value,
+        Some(value) => {
+            warn!(
+                "{name} = {value} is outside {}..={}, using {default}",
+                range.start(),
+                range.end()
+            );
+            default
+        }
+        None => default,
+    }
+}
+
+fn parse_color(name: &str, value: Option<String>, default: Color) -> Color {
+    let Some(hex) = value else {
+        return default;
     };
 
-    Some(side)
+    Color::hex(&hex).unwrap_or_else(|err| {
+        warn!("{name} = {hex:?} is not a valid color ({err}), using the default");
+        default
+    })
+}
+
+/// Collision shape used for the paddles. The sprite is always drawn as a box.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum PaddleShape {
+    #[default]
+    Box,
+    /// Rounded ends, so hits near a corner deflect along the curve
+    Capsule,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum SpeedUnit {
+    /// Pixels per second, as used everywhere in the game
+    #[default]
+    Px,
+    /// Miles per hour, scaled by `MPH_PER_PX_PER_SECOND` for flavor
+    Mph,
+}
+
+impl SpeedUnit {
+    fn format(&self, px_per_second: f32) -> String {
+        match self {
+            SpeedUnit::Px => format!("{px_per_second:.0} px/s"),
+            SpeedUnit::Mph => format!("{:.0} mph", px_per_second * MPH_PER_PX_PER_SECOND),
+        }
+    }
+}
+
+/// How a rally running past `max_rally_duration` ends
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum RallyTimeout {
+    /// Nobody scores, the ball is served again
+    #[default]
+    Reserve,
+    /// The player whose paddle is closest to the ball gets the point
+    Award,
+}
+
+/// Whether both paddles are played by people, or the right one by the AI
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+enum GameMode {
+    #[default]
+    TwoPlayer,
+    SinglePlayer,
+}
+
+impl GameMode {
+    /// Picks the mode from the command line, `--single-player` to play against the AI
+    fn from_args() -> GameMode {
+        if std::env::args().any(|arg| arg == "--single-player") {
+            GameMode::SinglePlayer
+        } else {
+            GameMode::TwoPlayer
+        }
+    }
+}
+
+/// How the court is laid out on screen. The game itself always runs with the
+/// paddles on the left/right of the arena; `Vertical` rotates the camera so
+/// they end up at the top/bottom of the screen, moving sideways.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+enum Orientation {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+impl Orientation {
+    /// Picks the orientation from the command line, `--vertical` for the table view
+    fn from_args() -> Orientation {
+        if std::env::args().any(|arg| arg == "--vertical") {
+            Orientation::Vertical
+        } else {
+            Orientation::Horizontal
+        }
+    }
+
+    fn window_resolution(&self) -> (f32, f32) {
+        match self {
+            Orientation::Horizontal => (WINDOW_W, WINDOW_H),
+            Orientation::Vertical => (WINDOW_H, WINDOW_W),
+        }
+    }
+
+    fn camera_rotation(&self) -> Quat {
+        match self {
+            Orientation::Horizontal => Quat::IDENTITY,
+            // the left side of the arena ends up at the top of the screen
+            Orientation::Vertical => Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+        }
+    }
+}
+
+/// Smallest angle, in degrees, a serve may make with the horizontal
+#[derive(Resource, Deref, DerefMut)]
+struct MinServeAngle(f32);
+
+impl Default for MinServeAngle {
+    fn default() -> Self {
+        MinServeAngle(MIN_SERVE_ANGLE)
+    }
+}
+
+/// x direction, -1 toward the left player or 1 toward the right one, of the
+/// serve waiting for `serve_ball` to launch it. The ball stays still meanwhile.
+#[derive(Resource, Default)]
+struct PendingServe(Option<f32>);
+
+/// Training option serving every ball straight at the center of the
+/// receiver's side instead of at `MinServeAngle`
+#[derive(Resource, Default)]
+struct ConsistentServe(bool);
+
+/// The player, if any, whose paddle slowly tracks the ball on its own.
+/// Any manual input overrides the assist for that frame, and their serves
+/// wait for a mouse click.
+#[derive(Resource, Default)]
+struct AssistMode(Option<Player>);
+
+/// How strongly the assist pulls the paddle, as a fraction of its normal speed
+#[derive(Resource, Deref, DerefMut)]
+struct AssistStrength(f32);
+
+impl Default for AssistStrength {
+    fn default() -> Self {
+        AssistStrength(ASSIST_STRENGTH)
+    }
+}
+
+#[derive(Component)]
+struct AssistIndicator;
+
+/// Brightens each wall as the ball gets closer to it
+#[derive(Resource)]
+struct WallGlow {
+    enabled: bool,
+    /// How far toward `WALL_GLOW_COLOR` a wall gets with the ball right next to it
+    intensity: f32,
+}
+
+impl Default for WallGlow {
+    fn default() -> Self {
+        WallGlow {
+            enabled: true,
+            intensity: WALL_GLOW_INTENSITY,
+        }
+    }
+}
+
+/// Faint background grid across the playfield, off by default
+#[derive(Resource, Default)]
+struct ShowGrid(bool);
+
+#[derive(Resource)]
+struct GridStyle {
+    spacing: f32,
+    color: Color,
+}
+
+impl Default for GridStyle {
+    fn default() -> Self {
+        GridStyle {
+            spacing: GRID_SPACING,
+            color: GRID_COLOR,
+        }
+    }
+}
+
+/// One line of the background grid. Purely decorative, it carries no `Collider`.
+#[derive(Component)]
+struct GridLine;
+
+/// Relative speed of the game clock, 1.0 being normal speed
+#[derive(Resource, Deref, DerefMut)]
+struct TimeScale(f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.0)
+    }
+}
+
+/// Short slow-motion window triggered when the ball narrowly misses a paddle
+#[derive(Resource)]
+struct BulletTime {
+    margin: f32,
+    slowdown: f32,
+    timer: Timer,
+    active: bool,
+    /// Cleared once triggered, and set again when the ball leaves near-miss
+    /// range, so a single approach can't trigger it repeatedly
+    armed: bool,
+}
+
+impl Default for BulletTime {
+    fn default() -> Self {
+        BulletTime {
+            margin: NEAR_MISS_MARGIN,
+            slowdown: BULLET_TIME_SCALE,
+            timer: Timer::from_seconds(BULLET_TIME_DURATION, TimerMode::Once),
+            active: false,
+            armed: true,
+        }
+    }
+}
+
+/// Whether the ball slows down a little while heading toward a trailing player
+#[derive(Resource, Default)]
+struct ComebackAssist(bool);
+
+/// Whether scoring a point briefly freezes the game
+#[derive(Resource, Default)]
+struct ScoreHitStop(bool);
+
+/// The freeze started by `ScoreHitStop`, which halts virtual time while active
+#[derive(Resource)]
+struct HitStop {
+    timer: Timer,
+    active: bool,
+}
+
+impl Default for HitStop {
+    fn default() -> Self {
+        HitStop {
+            timer: Timer::from_seconds(SCORE_HIT_STOP_DURATION, TimerMode::Once),
+            active: false,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct PaddleGhosting(bool);
+
+/// Paddle speed, in px/s, above which afterimages are left behind
+#[derive(Resource, Deref, DerefMut)]
+struct GhostSpeedThreshold(f32);
+
+impl Default for GhostSpeedThreshold {
+    fn default() -> Self {
+        GhostSpeedThreshold(PADDLE_GHOST_SPEED_THRESHOLD)
+    }
+}
+
+/// Soft shadow drawn under the ball for a sense of depth, off by default
+#[derive(Resource, Default)]
+struct BallShadow(bool);
+
+#[derive(Resource)]
+struct BallShadowStyle {
+    offset: Vec2,
+    opacity: f32,
+}
+
+impl Default for BallShadowStyle {
+    fn default() -> Self {
+        BallShadowStyle {
+            offset: BALL_SHADOW_OFFSET,
+            opacity: BALL_SHADOW_OPACITY,
+        }
+    }
+}
+
+/// Shadow following the given ball. It is not parented to the ball so it
+/// keeps its own z and isn't scaled by the wobble, and it has no `Collider`.
+#[derive(Component)]
+struct BallShadowOf(Entity);
+
+/// Purely visual squash and stretch of a fast ball. Only the rendered scale
+/// changes, collisions keep using `BALL_DIAMETER`.
+#[derive(Resource)]
+struct BallWobble {
+    enabled: bool,
+    amplitude: f32,
+}
+
+impl Default for BallWobble {
+    fn default() -> Self {
+        BallWobble {
+            enabled: false,
+            amplitude: BALL_WOBBLE_AMPLITUDE,
+        }
+    }
+}
+
+/// How far along its oscillation a ball's wobble is, in radians. Advanced by
+/// the distance travelled so speed changes don't make it jump.
+#[derive(Component, Default)]
+struct WobblePhase(f32);
+
+/// Delta times of the most recent frames, drawn as a small bar chart in the
+/// bottom-left corner. Toggled independently from the perf UI.
+#[derive(Resource)]
+struct FrameGraph {
+    deltas: VecDeque<f32>,
+    visible: bool,
+}
+
+impl Default for FrameGraph {
+    fn default() -> Self {
+        FrameGraph {
+            deltas: VecDeque::with_capacity(FRAME_GRAPH_CAPACITY),
+            visible: false,
+        }
+    }
+}
+
+// This bundle is a collection of the components that define a "wall" in our game
+#[derive(Bundle)]
+struct WallBundle {
+    // You can nest bundles inside of other bundles like this
+    // Allowing you to compose their functionality
+    sprite_bundle: SpriteBundle,
+    collider: Collider,
+    location: WallLocation,
+}
+
+/// Which side of the arena is this wall located on?
+#[derive(Component, Clone, Copy)]
+enum WallLocation {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl WallLocation {
+    fn position(&self, config: &GameConfig) -> Vec2 {
+        match self {
+            WallLocation::Left => Vec2::new(config.left_wall(), 0.),
+            WallLocation::Right => Vec2::new(config.right_wall(), 0.),
+            WallLocation::Bottom => Vec2::new(0., config.bottom_wall()),
+            WallLocation::Top => Vec2::new(0., config.top_wall()),
+        }
+    }
+
+    fn size(&self, config: &GameConfig) -> Vec2 {
+        match self {
+            WallLocation::Left | WallLocation::Right => {
+                Vec2::new(config.wall_thickness, config.arena_h + config.wall_thickness)
+            }
+            WallLocation::Bottom | WallLocation::Top => {
+                Vec2::new(config.arena_w + config.wall_thickness, config.wall_thickness)
+            }
+        }
+    }
+}
+
+impl WallBundle {
+    // This "builder method" allows us to reuse logic across our wall entities,
+    // making our code easier to read and less prone to bugs when we change the logic
+    fn new(location: WallLocation, config: &GameConfig) -> WallBundle {
+        WallBundle::from_rect(location, location.position(config), location.size(config), config)
+    }
+
+    /// A piece of a top or bottom wall spanning `start..end` along x
+    fn segment(location: WallLocation, start: f32, end: f32, config: &GameConfig) -> WallBundle {
+        let position = Vec2::new((start + end) / 2., location.position(config).y);
+        let size = Vec2::new(end - start, config.wall_thickness);
+
+        WallBundle::from_rect(location, position, size, config)
+    }
+
+    fn from_rect(location: WallLocation, position: Vec2, size: Vec2, config: &GameConfig) -> WallBundle {
+        WallBundle {
+            sprite_bundle: SpriteBundle {
+                transform: Transform {
+                    // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
+                    // This is used to determine the order of our sprites
+                    translation: position.extend(WALL_Z),
+                    // The z-scale of 2D objects must always be 1.0,
+                    // or their ordering will be affected in surprising ways.
+                    // See https://github.com/bevyengine/bevy/issues/4149
+                    scale: size.extend(1.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: config.wall_color,
+                    ..default()
+                },
+                ..default()
+            },
+            collider: Collider,
+            location,
+        }
+    }
+}
+
+fn load_config(mut commands: Commands) {
+    let config = GameConfig::load(CONFIG_PATH);
+    commands.insert_resource(GoalLine::at_walls(&config));
+    commands.insert_resource(config);
+    commands.insert_resource(ConfigWatcher::new(CONFIG_PATH));
+}
+
+fn watch_config(
+    mut watcher: ResMut<ConfigWatcher>,
+    mut config: ResMut<GameConfig>,
+    time: Res<Time<Real>>,
+) {
+    if watcher.poll_timer.tick(time.delta()).just_finished() {
+        let modified = modified_time(CONFIG_PATH);
+        if modified != watcher.last_modified {
+            watcher.last_modified = modified;
+            watcher.debounce = Some(Timer::from_seconds(CONFIG_RELOAD_DEBOUNCE, TimerMode::Once));
+        }
+    }
+
+    let Some(debounce) = &mut watcher.debounce else {
+        return;
+    };
+    if !debounce.tick(time.delta()).finished() {
+        return;
+    }
+    watcher.debounce = None;
+
+    let mut reloaded = GameConfig::load(CONFIG_PATH);
+    if reloaded.is_structural_change(&config) {
+        warn!("arena and wall changes in {CONFIG_PATH} need a restart to take effect");
+        reloaded.arena_w = config.arena_w;
+        reloaded.arena_h = config.arena_h;
+        reloaded.wall_thickness = config.wall_thickness;
+        reloaded.wall_gaps = config.wall_gaps.clone();
+    }
+
+    if reloaded != *config {
+        info!("reloaded {CONFIG_PATH}");
+        *config = reloaded;
+    }
+}
+
+/// Pushes reloaded config into already spawned entities. Everything else
+/// reads `GameConfig` every frame and needs no help.
+#[allow(clippy::type_complexity)]
+fn apply_config(
+    config: Res<GameConfig>,
+    mut ball_query: Query<(&mut Velocity, &mut Transform, &Handle<ColorMaterial>), With<Ball>>,
+    mut paddle_query: Query<(&mut Transform, &mut Sprite, &Player), (With<Paddle>, Without<Ball>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+
+    for (mut velocity, mut transform, material) in &mut ball_query {
+        velocity.0 = velocity.normalize_or_zero() * to_phys_scalar(config.ball_speed);
+        transform.translation.z = config.ball_z();
+
+        if let Some(material) = materials.get_mut(material) {
+            material.color = config.ball_color;
+        }
+    }
+
+    let (bottom_bound, top_bound) = paddle_bounds(&config);
+    for (mut transform, mut sprite, player) in &mut paddle_query {
+        sprite.color = config.paddle_color;
+
+        // a resized paddle keeps its distance to the wall and stays inside the arena
+        transform.scale = Vec3::new(config.paddle_w, config.paddle_h, 1.);
+        transform.translation.x = config.paddle_x(*player);
+        transform.translation.y = transform.translation.y.clamp(bottom_bound, top_bound);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut pending_serve: ResMut<PendingServe>,
+    orientation: Res<Orientation>,
+    config: Res<GameConfig>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn(Camera2dBundle {
+        transform: Transform::from_rotation(orientation.camera_rotation()),
+        ..default()
+    });
+
+    commands.spawn(PerfUiCompleteBundle::default());
+
+    // a missing file only logs an error, the game just stays silent
+    commands.insert_resource(CollisionSound {
+        paddle: asset_server.load("sounds/paddle_hit.wav"),
+        wall: asset_server.load("sounds/wall_hit.wav"),
+    });
+
+    commands.spawn((
+        TextBundle::from_section(
+            "ASSIST",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::LIME_GREEN,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            right: Val::Px(20.0),
+            ..default()
+        }),
+        AssistIndicator,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Percent(50.0),
+            ..default()
+        }),
+        RallyClockText,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::GOLD,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(60.0),
+            left: Val::Percent(35.0),
+            ..default()
+        }),
+        ServeZonePrompt,
+        Visibility::Hidden,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "CLICK TO SERVE",
+            TextStyle {
+                font_size: 48.0,
+                color: Color::LIME_GREEN,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(100.0),
+            left: Val::Percent(40.0),
+            ..default()
+        }),
+        ClickToServePrompt,
+        Visibility::Hidden,
+    ));
+
+    // Spawn Ball
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: Mesh2dHandle(meshes.add(Circle { radius: BALL_DIAMETER })),
+            material: materials.add(config.ball_color),
+            transform: Transform::from_translation(BALL_INITIAL_POSITION.extend(config.ball_z())),
+            ..default()
+        },
+        Ball,
+        LastCollider::default(),
+        WobblePhase::default(),
+        Position(to_phys(BALL_INITIAL_POSITION)),
+        Velocity(PhysVec::ZERO),
+    ));
+    pending_serve.0 = Some(BALL_INITIAL_DIRECTION.x.signum());
+    // Paddles
+    for player in [Player::Left, Player::Right] {
+        let translation = Vec3::new(config.paddle_x(player), 0., PADDLE_Z);
+
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
+                    // This is used to determine the order of our sprites
+                    translation,
+                    // The z-scale of 2D objects must always be 1.0,
+                    // or their ordering will be affected in surprising ways.
+                    // See https://github.com/bevyengine/bevy/issues/4149
+                    scale: Vec3::new(config.paddle_w, config.paddle_h, 1.),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: config.paddle_color,
+                    ..default()
+                },
+                ..default()
+            },
+            Paddle,
+            player,
+            Collider,
+            HitCooldown::default(),
+            BoostCharge(true),
+            GhostEmitter::new(translation),
+            Velocity(PhysVec::ZERO)
+        ));
+
+        let (left, right) = match player {
+            Player::Left => (Val::Px(20.0), Val::Auto),
+            Player::Right => (Val::Auto, Val::Px(20.0)),
+        };
+        commands.spawn((
+            TextBundle::from_section(
+                "BOOST READY",
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::GOLD,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(20.0),
+                left,
+                right,
+                ..default()
+            }),
+            BoostIndicator(player),
+        ));
+    }
+
+    // Spawn Walls, the top and bottom ones possibly cut into segments around gaps
+    let half_width = (config.arena_w + config.wall_thickness) / 2.;
+    for (location, gaps) in [
+        (WallLocation::Top, &config.wall_gaps.top),
+        (WallLocation::Bottom, &config.wall_gaps.bottom),
+    ] {
+        for (start, end) in wall_segments(gaps, half_width) {
+            commands.spawn(WallBundle::segment(location, start, end, &config));
+        }
+    }
+    // The side walls only mark the goal lines, the ball has to be able to pass them to score
+    commands.spawn(WallBundle::new(WallLocation::Left, &config)).remove::<Collider>();
+    commands.spawn(WallBundle::new(WallLocation::Right, &config)).remove::<Collider>();
+}
+
+/// Velocity of a serve toward `toward_x`, as stored in `PendingServe`.
+/// Consistent serves are flat, the others leave at least `min_angle` radians
+/// off the horizontal.
+fn serve_velocity(toward_x: f32, consistent: bool, min_angle: f32, speed: f32) -> Vec2 {
+    if consistent {
+        return Vec2::new(toward_x * speed, 0.0);
+    }
+
+    let direction = Vec2::new(BALL_INITIAL_DIRECTION.x.abs() * toward_x, BALL_INITIAL_DIRECTION.y);
+    clamp_serve_angle(direction, min_angle) * speed
+}
+
+/// Normalizes a serve direction, rotating it away from the horizontal if it is
+/// less than `min_angle` radians off it. Left/right and up/down are preserved,
+/// a perfectly flat serve goes upward.
+fn clamp_serve_angle(direction: Vec2, min_angle: f32) -> Vec2 {
+    let direction = direction.normalize();
+    let angle = direction.y.atan2(direction.x.abs());
+
+    if angle.abs() >= min_angle {
+        return direction;
+    }
+
+    let vertical = if direction.y < 0.0 { -1.0 } else { 1.0 };
+    Vec2::new(min_angle.cos() * direction.x.signum(), min_angle.sin() * vertical)
+}
+
+fn spawn_ball_shadow(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ball_query: Query<Entity, With<Ball>>,
+    style: Res<BallShadowStyle>,
+) {
+    for ball in &ball_query {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle { radius: BALL_DIAMETER })),
+                material: materials.add(Color::BLACK.with_a(style.opacity)),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            BallShadowOf(ball),
+        ));
+    }
+}
+
+fn spawn_grid(
+    mut commands: Commands,
+    show_grid: Res<ShowGrid>,
+    style: Res<GridStyle>,
+    config: Res<GameConfig>,
+) {
+    let visibility = if show_grid.0 {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+    let mut spawn_line = |position: Vec2, size: Vec2| {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: position.extend(GRID_Z),
+                    scale: size.extend(1.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: style.color,
+                    ..default()
+                },
+                visibility,
+                ..default()
+            },
+            GridLine,
+        ));
+    };
+
+    // lines are laid out from the center so the grid stays symmetric
+    let columns = (config.arena_w / 2. / style.spacing) as i32;
+    for i in -columns..=columns {
+        spawn_line(
+            Vec2::new(i as f32 * style.spacing, 0.),
+            Vec2::new(GRID_LINE_THICKNESS, config.arena_h),
+        );
+    }
+
+    let rows = (config.arena_h / 2. / style.spacing) as i32;
+    for i in -rows..=rows {
+        spawn_line(
+            Vec2::new(0., i as f32 * style.spacing),
+            Vec2::new(config.arena_w, GRID_LINE_THICKNESS),
+        );
+    }
+}
+
+fn apply_velocity(
+    mut query: Query<(&mut Transform, Option<&mut Position>, &Velocity)>,
+    time: Res<Time>
+) {
+    for (mut transform, position, velocity) in &mut query {
+        let translation = match position {
+            Some(mut position) => {
+                position.0 += velocity.0 * delta_seconds(&time);
+                to_render(position.0)
+            }
+            None => transform.translation.truncate() + to_render(velocity.0) * time.delta_seconds(),
+        };
+
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn move_paddle(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut query: Query<(&mut Transform, &Player), (With<Paddle>, Without<OpponentController>)>,
+    ball_query: Query<&Transform, (With<Ball>, Without<Paddle>)>,
+    assist: Res<AssistMode>,
+    assist_strength: Res<AssistStrength>,
+    orientation: Res<Orientation>,
+    config: Res<GameConfig>,
+    time: Res<Time>
+) {
+    let (bottom_bound, top_bound) = paddle_bounds(&config);
+
+    for (mut paddle_transform, player) in &mut query {
+        let mut direction = 0.0;
+        let (up_key, down_key) = player.paddle_keys(*orientation);
+
+        if keyboard_input.pressed(up_key) {
+            direction += 1.0;
+        }
+
+        if keyboard_input.pressed(down_key) {
+            direction -= 1.0;
+        }
+
+        // manual input always wins over the assist
+        if direction == 0.0 && assist.0 == Some(*player) {
+            let ball_transform = ball_query.single();
+            let offset = ball_transform.translation.y - paddle_transform.translation.y;
+            direction = (offset / (config.paddle_h / 2.)).clamp(-1.0, 1.0) * assist_strength.0;
+        }
+
+        if keyboard_input.pressed(player.precision_key()) {
+            direction *= PADDLE_PRECISION_FACTOR;
+        }
+
+        let new_paddle_position =
+            paddle_transform.translation.y + direction * time.delta_seconds() * config.paddle_speed;
+
+        paddle_transform.translation.y = new_paddle_position.clamp(bottom_bound, top_bound);
+    }
+}
+
+/// Speeds up the ball when a player who still has their boost presses the
+/// boost key while it is on their half
+#[allow(clippy::type_complexity)]
+fn boost_ball(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut paddle_query: Query<(&Player, &mut BoostCharge), (With<Paddle>, Without<OpponentController>)>,
+    mut ball_query: Query<(&Transform, &mut Velocity), With<Ball>>,
+    config: Res<GameConfig>,
+) {
+    if !config.ball_boost {
+        return;
+    }
+
+    for (player, mut charge) in &mut paddle_query {
+        if !charge.0 || !keyboard_input.pressed(player.boost_key()) {
+            continue;
+        }
+
+        for (transform, mut velocity) in &mut ball_query {
+            let on_own_half = match player {
+                Player::Left => transform.translation.x < 0.0,
+                Player::Right => transform.translation.x > 0.0,
+            };
+            // a ball waiting to be served can't be boosted
+            let Some(direction) = to_render(velocity.0).try_normalize() else {
+                continue;
+            };
+            if !on_own_half {
+                continue;
+            }
+
+            let speed = (to_render(velocity.0).length() * BALL_BOOST_FACTOR).min(MAX_BALL_SPEED);
+            velocity.0 = to_phys(direction * speed);
+            charge.0 = false;
+        }
+    }
+}
+
+/// Lowest and highest y a paddle center may reach without going into the walls
+fn paddle_bounds(config: &GameConfig) -> (f32, f32) {
+    let margin = config.wall_thickness + config.paddle_h / 2. + PADDLE_DISTANCE_TO_WALL;
+
+    (config.bottom_wall() + margin, config.top_wall() - margin)
+}
+
+fn spawn_opponent(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    paddle_query: Query<(Entity, &Player), With<Paddle>>,
+) {
+    if *game_mode != GameMode::SinglePlayer {
+        return;
+    }
+
+    for (entity, player) in &paddle_query {
+        if *player == Player::Right {
+            commands.entity(entity).insert(OpponentController::default());
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_opponent(
+    game_mode: Res<GameMode>,
+    mut paddle_query: Query<(&mut Transform, &OpponentController), With<Paddle>>,
+    ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<Paddle>)>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    if *game_mode == GameMode::TwoPlayer {
+        return;
+    }
+
+    let (ball_transform, ball_velocity) = ball_query.single();
+    let ball = ball_transform.translation.truncate();
+    let ball_velocity = to_render(ball_velocity.0);
+    let (bottom_bound, top_bound) = paddle_bounds(&config);
+
+    for (mut transform, controller) in &mut paddle_query {
+        let paddle = transform.translation.truncate();
+        let approaching = ball_velocity.x != 0.0 && (paddle.x - ball.x).signum() == ball_velocity.x.signum();
+
+        // only track a ball that is coming this way, otherwise drift back to the middle
+        let target = if approaching {
+            let time_to_reach = (paddle.x - ball.x) / ball_velocity.x;
+            let predicted = predict_ball_y(ball.y + ball_velocity.y * time_to_reach, &config);
+            let error = controller.tracking_error * (time.elapsed_seconds() * AI_ERROR_FREQUENCY).sin();
+
+            predicted + error
+        } else {
+            0.0
+        };
+
+        let max_step = config.paddle_speed * controller.speed_factor * time.delta_seconds();
+        let step = (target - paddle.y).clamp(-max_step, max_step);
+
+        transform.translation.y = (paddle.y + step).clamp(bottom_bound, top_bound);
+    }
+}
+
+/// Folds a straight-line ball y back into the arena, as the top and bottom
+/// walls would bounce it
+fn predict_ball_y(y: f32, config: &GameConfig) -> f32 {
+    let min = config.bottom_wall() + config.wall_thickness / 2. + BALL_DIAMETER;
+    let max = config.top_wall() - config.wall_thickness / 2. - BALL_DIAMETER;
+    let span = max - min;
+
+    let folded = (y - min).rem_euclid(2. * span);
+    if folded > span {
+        max - (folded - span)
+    } else {
+        min + folded
+    }
+}
+
+fn spawn_paddle_ghosts(
+    mut commands: Commands,
+    ghosting: Res<PaddleGhosting>,
+    threshold: Res<GhostSpeedThreshold>,
+    mut paddle_query: Query<(&Transform, &Sprite, &mut GhostEmitter), With<Paddle>>,
+    ghost_query: Query<(), With<PaddleGhost>>,
+    time: Res<Time>,
+) {
+    let mut ghost_count = ghost_query.iter().count();
+
+    for (transform, sprite, mut emitter) in &mut paddle_query {
+        let distance = transform.translation.distance(emitter.last_position);
+        emitter.last_position = transform.translation;
+        emitter.timer.tick(time.delta());
+
+        if !ghosting.0 || time.delta_seconds() == 0.0 {
+            continue;
+        }
+
+        let speed = distance / time.delta_seconds();
+        if speed < threshold.0 || !emitter.timer.just_finished() || ghost_count >= PADDLE_GHOST_MAX {
+            continue;
+        }
+
+        ghost_count += 1;
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: transform.translation.truncate().extend(PADDLE_GHOST_Z),
+                    ..*transform
+                },
+                sprite: Sprite {
+                    color: sprite.color.with_a(PADDLE_GHOST_ALPHA),
+                    ..default()
+                },
+                ..default()
+            },
+            PaddleGhost(Timer::from_seconds(PADDLE_GHOST_LIFETIME, TimerMode::Once)),
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn check_for_collision(
+    mut ball_query: Query<(Entity, &mut Velocity, &Transform, &mut LastCollider), With<Ball>>,
+    mut collider_query: Query<
+        (Entity, &Transform, Has<Paddle>, Option<&mut HitCooldown>),
+        With<Collider>
+    >,
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut bullet_time: ResMut<BulletTime>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    let (ball_entity, mut ball_velocity, ball_transform, mut last_collider) = ball_query.single_mut();
+    last_collider.1.tick(time.delta());
+
+    let mut near_miss = false;
+
+    // check collision with Walls
+    for (collider_entity, transform, is_paddle, mut cooldown) in &mut collider_query {
+        if let Some(cooldown) = &mut cooldown {
+            cooldown.timer.tick(time.delta());
+
+            if cooldown.ball == Some(ball_entity) && !cooldown.timer.finished() {
+                continue;
+            }
+        }
+
+        if last_collider.0 == Some(collider_entity) && !last_collider.1.finished() {
+            continue;
+        }
+
+        // `BALL_DIAMETER * 0.8` makes the ball overlap 20% before considering a
+        // collision, this makes the "bounce" feel more natural
+        let ball = BoundingCircle::new(ball_transform.translation.truncate(), BALL_DIAMETER * 0.8);
+        let collider = Aabb2d::new(
+            transform.translation.truncate(),
+            transform.scale.truncate() / 2.
+        );
+
+        if is_paddle && is_near_miss(ball, collider, bullet_time.margin) {
+            near_miss = true;
+        }
+
+        let reflected = if is_paddle && config.paddle_shape == PaddleShape::Capsule {
+            let capsule = PaddleCapsule::from_aabb(collider);
+            let Some(normal) = collide_with_capsule(ball, capsule) else {
+                continue;
+            };
+
+            // only bounce a ball that is actually moving into the paddle
+            let approach = ball_velocity.dot(to_phys(normal));
+            if approach < 0.0 && capsule.is_on_face(ball.center()) {
+                // the flat part aims the ball just like a box paddle does
+                let direction = paddle_bounce_direction(ball.center(), collider.center(), transform.scale.y, normal.x);
+                ball_velocity.0 = to_phys(direction * config.ball_speed);
+            } else if approach < 0.0 {
+                // the rounded ends mirror the velocity around the contact normal
+                ball_velocity.0 -= to_phys(normal) * (2.0 * approach);
+            }
+
+            approach < 0.0
+        } else {
+            let Some(collision) = collide_with_side(ball, collider) else {
+                continue;
+            };
+
+            let mut reflect_x = false;
+            let mut reflect_y = false;
+
+            match collision {
+                Collision::Top => reflect_y = ball_velocity.y < 0.0,
+                Collision::Bottom => reflect_y = ball_velocity.y > 0.0,
+                Collision::Left => reflect_x = ball_velocity.x > 0.0,
+                Collision::Right => reflect_x = ball_velocity.x < 0.0,
+            }
+
+            if reflect_x && is_paddle {
+                // the paddle face decides the new direction, so players can aim
+                let away = if collision == Collision::Right { 1.0 } else { -1.0 };
+                let direction = paddle_bounce_direction(ball.center(), collider.center(), transform.scale.y, away);
+                ball_velocity.0 = to_phys(direction * config.ball_speed);
+            } else if reflect_x {
+                ball_velocity.x = -ball_velocity.x;
+            }
+
+            if reflect_y {
+                ball_velocity.y = -ball_velocity.y;
+            }
+
+            reflect_x || reflect_y
+        };
+
+        if reflected {
+            collision_events.send(if is_paddle {
+                CollisionEvent::Paddle
+            } else {
+                CollisionEvent::Wall
+            });
+
+            last_collider.0 = Some(collider_entity);
+            last_collider.1.reset();
+
+            if let Some(cooldown) = &mut cooldown {
+                cooldown.ball = Some(ball_entity);
+                cooldown.timer.reset();
+            }
+        }
+    }
+
+    if !near_miss {
+        bullet_time.armed = true;
+    } else if bullet_time.armed {
+        bullet_time.armed = false;
+        bullet_time.active = true;
+        bullet_time.timer.reset();
+    }
+}
+
+fn play_collision_sound(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    sound: Res<CollisionSound>,
+) {
+    // a single bounce sound per frame, even if the ball hit several things at once
+    let Some(event) = collision_events.read().last() else {
+        return;
+    };
+
+    let source = match event {
+        CollisionEvent::Paddle => sound.paddle.clone(),
+        CollisionEvent::Wall => sound.wall.clone(),
+    };
+
+    commands.spawn(AudioBundle {
+        source,
+        // despawn the playback entity once the sound is done
+        settings: PlaybackSettings::DESPAWN,
+    });
+}
+
+/// Awards a point once the ball's center is past a goal line, or the rally ran
+/// past `max_rally_duration`, then sets up the next serve
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn check_for_score(
+    mut score: ResMut<Score>,
+    mut ball_query: Query<
+        (&mut Position, &mut Transform, &mut Velocity, &mut LastCollider),
+        With<Ball>
+    >,
+    mut paddle_query: Query<
+        (&Transform, &Player, Option<&mut BoostCharge>),
+        (With<Paddle>, Without<Ball>)
+    >,
+    mut pending_serve: ResMut<PendingServe>,
+    mut rally_stats: ResMut<RallyStats>,
+    mut rally_clock: ResMut<MaxRallyDuration>,
+    goal_line: Res<GoalLine>,
+    score_hit_stop: Res<ScoreHitStop>,
+    mut hit_stop: ResMut<HitStop>,
+    config: Res<GameConfig>,
+) {
+    for (mut position, mut transform, mut velocity, mut last_collider) in &mut ball_query {
+        let ball = transform.translation.truncate();
+        let timed_out = config.max_rally_duration > 0.0 && rally_clock.0.finished();
+        let winner = if ball.x < goal_line.left {
+            Some(Player::Right)
+        } else if ball.x > goal_line.right {
+            Some(Player::Left)
+        } else if timed_out && config.rally_timeout == RallyTimeout::Award {
+            paddle_query
+                .iter()
+                .min_by(|(a, _, _), (b, _, _)| {
+                    let a = a.translation.truncate().distance_squared(ball);
+                    a.total_cmp(&b.translation.truncate().distance_squared(ball))
+                })
+                .map(|(_, player, _)| *player)
+        } else if timed_out {
+            None
+        } else {
+            continue;
+        };
+        rally_clock.0.reset();
+
+        // a point goes to the loser, a replayed rally keeps heading the way it went
+        let serve_x = match winner {
+            Some(Player::Left) => 1.0,
+            Some(Player::Right) => -1.0,
+            None => to_render(velocity.0).x.signum(),
+        };
+
+        if let Some(winner) = winner {
+            match winner {
+                Player::Left => score.left += 1,
+                Player::Right => score.right += 1,
+            }
+            info!(
+                "score: {} - {}, fastest ball {}",
+                score.left,
+                score.right,
+                config.speed_unit.format(rally_stats.peak_speed)
+            );
+
+            if score.left >= config.win_score || score.right >= config.win_score {
+                info!("{winner:?} wins {} - {}, starting a new match", score.left, score.right);
+                *score = Score::default();
+            }
+
+            if score_hit_stop.0 {
+                hit_stop.active = true;
+                hit_stop.timer.reset();
+            }
+        } else {
+            info!("rally ran past {}s, serving again", config.max_rally_duration);
+        }
+        *rally_stats = RallyStats::default();
+        for (_, _, charge) in &mut paddle_query {
+            if let Some(mut charge) = charge {
+                charge.0 = true;
+            }
+        }
+
+        // the serve starts on the receiver's half, mirrored so both players get
+        // the same distance to react. Moving the ball back in right away also
+        // means it can't score twice.
+        let serve_position = Vec2::new(BALL_INITIAL_POSITION.x.abs() * serve_x, BALL_INITIAL_POSITION.y);
+        position.0 = to_phys(serve_position);
+        transform.translation = serve_position.extend(transform.translation.z);
+
+        velocity.0 = PhysVec::ZERO;
+        pending_serve.0 = Some(serve_x);
+        *last_collider = LastCollider::default();
+    }
+}
+
+/// Runs the rally clock while the ball is in play, if rallies are capped
+fn tick_rally_clock(
+    mut rally_clock: ResMut<MaxRallyDuration>,
+    pending_serve: Res<PendingServe>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    if config.max_rally_duration <= 0.0 || pending_serve.0.is_some() {
+        return;
+    }
+
+    let duration = Duration::from_secs_f32(config.max_rally_duration);
+    if rally_clock.0.duration() != duration {
+        rally_clock.0.set_duration(duration);
+    }
+    rally_clock.0.tick(time.delta());
+}
+
+/// Launches the pending serve, if there is one
+/// The serving player, if their paddle is outside `GameConfig::serve_zone`
+fn out_of_serve_zone<'a>(
+    toward_x: f32,
+    config: &GameConfig,
+    paddles: impl IntoIterator<Item = (&'a Transform, &'a Player)>,
+) -> Option<Player> {
+    if config.serve_zone <= 0.0 {
+        return None;
+    }
+
+    let server = Player::serving_toward(toward_x);
+    paddles
+        .into_iter()
+        .any(|(transform, player)| *player == server && transform.translation.y.abs() > config.serve_zone / 2.0)
+        .then_some(server)
+}
+
+/// Whether a serve toward `toward_x` is the assisted player's, who launches it
+/// with either mouse button
+fn waits_for_click(toward_x: f32, assist: &AssistMode) -> bool {
+    assist.0 == Some(Player::serving_toward(toward_x))
+}
+
+/// Launches the pending serve, holding it while the server is out of the serve
+/// zone or, in assist mode, until they click
+#[allow(clippy::too_many_arguments)]
+fn serve_ball(
+    mut pending_serve: ResMut<PendingServe>,
+    mut ball_query: Query<&mut Velocity, With<Ball>>,
+    paddle_query: Query<(&Transform, &Player), With<Paddle>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    assist: Res<AssistMode>,
+    consistent_serve: Res<ConsistentServe>,
+    min_serve_angle: Res<MinServeAngle>,
+    config: Res<GameConfig>,
+) {
+    let Some(toward_x) = pending_serve.0 else {
+        return;
+    };
+    if out_of_serve_zone(toward_x, &config, &paddle_query).is_some() {
+        return;
+    }
+    if waits_for_click(toward_x, &assist) && !mouse_input.any_pressed([MouseButton::Left, MouseButton::Right]) {
+        return;
+    }
+    pending_serve.0 = None;
+
+    let velocity = serve_velocity(toward_x, consistent_serve.0, min_serve_angle.to_radians(), config.ball_speed);
+    for mut ball_velocity in &mut ball_query {
+        ball_velocity.0 = to_phys(velocity);
+    }
+}
+
+fn track_rally_speed(mut rally_stats: ResMut<RallyStats>, query: Query<&Velocity, With<Ball>>) {
+    for velocity in &query {
+        rally_stats.peak_speed = rally_stats.peak_speed.max(to_render(velocity.0).length());
+    }
+}
+
+/// Takes speed off a ball heading toward the player who is behind, more the
+/// further behind they are, up to `COMEBACK_MAX_SLOWDOWN`
+fn apply_comeback_assist(
+    comeback_assist: Res<ComebackAssist>,
+    score: Res<Score>,
+    config: Res<GameConfig>,
+    mut query: Query<&mut Velocity, With<Ball>>,
+) {
+    if !comeback_assist.0 {
+        return;
+    }
+
+    let gap = score.left.abs_diff(score.right) as f32;
+    let slowdown = (gap * COMEBACK_SLOWDOWN_PER_POINT).min(COMEBACK_MAX_SLOWDOWN);
+
+    for mut velocity in &mut query {
+        let toward_trailing = (score.left < score.right && velocity.x < 0.0)
+            || (score.right < score.left && velocity.x > 0.0);
+        let speed = if toward_trailing { config.ball_speed * (1.0 - slowdown) } else { config.ball_speed };
+
+        velocity.0 = velocity.normalize_or_zero() * to_phys_scalar(speed);
+    }
+}
+
+/// Moves a ball that escaped through a top/bottom wall gap to just inside the
+/// opposite wall, keeping its velocity
+fn wrap_through_wall_gaps(
+    mut query: Query<(&mut Position, &mut Transform), With<Ball>>,
+    config: Res<GameConfig>,
+) {
+    let top = config.top_wall() + config.wall_thickness / 2.;
+    let bottom = config.bottom_wall() - config.wall_thickness / 2.;
+    // far enough from the wall that the ball doesn't touch it on arrival
+    let inset = config.wall_thickness + BALL_DIAMETER;
+
+    for (mut position, mut transform) in &mut query {
+        let y = transform.translation.y;
+        let wrapped_y = if y > top {
+            config.bottom_wall() + inset
+        } else if y < bottom {
+            config.top_wall() - inset
+        } else {
+            continue;
+        };
+
+        position.y = to_phys_scalar(wrapped_y);
+        transform.translation.y = wrapped_y;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Collision {
+    Left,
+    Right,
+    Top,
+    Bottom
+}
+
+fn collide_with_side(ball: BoundingCircle, wall: Aabb2d,) -> Option<Collision> {
+    if !ball.intersects(&wall) {
+        return None;
+    }
+
+    let closest = wall.closest_point(ball.center());
+    let offset = ball.center() - closest;
+    let side = if offset.x.abs() > offset.y.abs() {
+        if offset.x > 0. {
+            Collision::Right
+        } else {
+            Collision::Left
+        }
+    } else if offset.y > 0.0 {
+        Collision::Top
+    } else {
+        Collision::Bottom
+    };
+
+    Some(side)
+}
+
+/// Direction a ball leaves a paddle face in: straight out from the center of
+/// the paddle, angled further up or down the closer to the edge it hits.
+/// `away` is the x direction pointing away from the paddle face.
+fn paddle_bounce_direction(ball: Vec2, paddle: Vec2, paddle_h: f32, away: f32) -> Vec2 {
+    let offset_y = ((ball.y - paddle.y) / (paddle_h / 2.)).clamp(-1.0, 1.0);
+    let angle = offset_y * PADDLE_MAX_BOUNCE_ANGLE.to_radians();
+
+    Vec2::new(angle.cos() * away, angle.sin())
+}
+
+/// A paddle with rounded ends: the vertical segment `center ± half_length`
+/// swept by `radius`
+#[derive(Debug, Copy, Clone)]
+struct PaddleCapsule {
+    center: Vec2,
+    half_length: f32,
+    radius: f32,
+}
+
+impl PaddleCapsule {
+    /// The capsule fitting inside a paddle's box, its ends being half circles
+    fn from_aabb(aabb: Aabb2d) -> PaddleCapsule {
+        let half_size = aabb.half_size();
+
+        PaddleCapsule {
+            center: aabb.center(),
+            half_length: (half_size.y - half_size.x).max(0.0),
+            radius: half_size.x,
+        }
+    }
+
+    /// Whether `point` is level with the flat part of the paddle rather than
+    /// one of its rounded ends
+    fn is_on_face(&self, point: Vec2) -> bool {
+        (point.y - self.center.y).abs() <= self.half_length
+    }
+}
+
+/// Returns the contact normal, pointing from the paddle toward the ball, if
+/// they overlap
+fn collide_with_capsule(ball: BoundingCircle, paddle: PaddleCapsule) -> Option<Vec2> {
+    let center = ball.center();
+    let closest = Vec2::new(
+        paddle.center.x,
+        center.y.clamp(paddle.center.y - paddle.half_length, paddle.center.y + paddle.half_length),
+    );
+    let offset = center - closest;
+
+    if offset.length_squared() > (ball.radius() + paddle.radius).powi(2) {
+        return None;
+    }
+
+    // a ball centered right on the segment has no direction, push it out sideways
+    Some(offset.try_normalize().unwrap_or(Vec2::new(
+        if center.x < paddle.center.x { -1.0 } else { 1.0 },
+        0.0,
+    )))
+}
+
+/// Whether the ball is sliding past the top or bottom edge of the paddle
+/// without touching it, within `margin`
+fn is_near_miss(ball: BoundingCircle, paddle: Aabb2d, margin: f32) -> bool {
+    if ball.intersects(&paddle) {
+        return false;
+    }
+
+    // a ball heading at the paddle face is just an incoming hit (or a goal)
+    let center = ball.center();
+    if center.x < paddle.min.x || center.x > paddle.max.x {
+        return false;
+    }
+
+    center.distance(paddle.closest_point(center)) <= ball.radius() + margin
+}
+
+/// Cycles the assist through off and each human player
+fn toggle_assist(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    game_mode: Res<GameMode>,
+    mut assist: ResMut<AssistMode>,
+) {
+    if !keyboard_input.just_pressed(ASSIST_TOGGLE_KEY) {
+        return;
+    }
+
+    assist.0 = match (assist.0, *game_mode) {
+        (None, _) => Some(Player::Left),
+        // the right paddle belongs to the AI in single player
+        (Some(Player::Left), GameMode::TwoPlayer) => Some(Player::Right),
+        (Some(Player::Left), GameMode::SinglePlayer) | (Some(Player::Right), _) => None,
+    };
+}
+
+fn update_assist_indicator(
+    assist: Res<AssistMode>,
+    mut query: Query<(&mut Visibility, &mut Text), With<AssistIndicator>>,
+) {
+    if !assist.is_changed() {
+        return;
+    }
+
+    for (mut visibility, mut text) in &mut query {
+        match assist.0 {
+            Some(player) => {
+                *visibility = Visibility::Visible;
+                text.sections[0].value = match player {
+                    Player::Left => "ASSIST: LEFT".into(),
+                    Player::Right => "ASSIST: RIGHT".into(),
+                };
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+fn update_rally_clock_text(
+    rally_clock: Res<MaxRallyDuration>,
+    config: Res<GameConfig>,
+    mut query: Query<(&mut Visibility, &mut Text), With<RallyClockText>>,
+) {
+    for (mut visibility, mut text) in &mut query {
+        if config.max_rally_duration <= 0.0 {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        *visibility = Visibility::Visible;
+        text.sections[0].value = format!("{:.0}", rally_clock.0.remaining_secs().ceil());
+    }
+}
+
+fn update_serve_zone_prompt(
+    pending_serve: Res<PendingServe>,
+    config: Res<GameConfig>,
+    paddle_query: Query<(&Transform, &Player), With<Paddle>>,
+    mut query: Query<(&mut Visibility, &mut Text), With<ServeZonePrompt>>,
+) {
+    let server = pending_serve.0.and_then(|toward_x| out_of_serve_zone(toward_x, &config, &paddle_query));
+
+    for (mut visibility, mut text) in &mut query {
+        match server {
+            Some(player) => {
+                *visibility = Visibility::Visible;
+                text.sections[0].value = match player {
+                    Player::Left => "MOVE TO THE MIDDLE TO SERVE, LEFT".into(),
+                    Player::Right => "MOVE TO THE MIDDLE TO SERVE, RIGHT".into(),
+                };
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+fn update_click_to_serve_prompt(
+    pending_serve: Res<PendingServe>,
+    assist: Res<AssistMode>,
+    mut query: Query<&mut Visibility, With<ClickToServePrompt>>,
+) {
+    let waiting = pending_serve.0.is_some_and(|toward_x| waits_for_click(toward_x, &assist));
+
+    for mut visibility in &mut query {
+        *visibility = if waiting { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+fn update_boost_indicators(
+    config: Res<GameConfig>,
+    paddle_query: Query<(&Player, &BoostCharge, Has<OpponentController>), With<Paddle>>,
+    mut indicator_query: Query<(&mut Visibility, &BoostIndicator)>,
+) {
+    for (mut visibility, indicator) in &mut indicator_query {
+        // the AI never boosts, so its label stays hidden
+        let ready = config.ball_boost && paddle_query
+            .iter()
+            .any(|(player, charge, is_ai)| *player == indicator.0 && charge.0 && !is_ai);
+
+        *visibility = if ready { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+fn toggle_ball_shadow(keyboard_input: Res<ButtonInput<KeyCode>>, mut shadow: ResMut<BallShadow>) {
+    if keyboard_input.just_pressed(BALL_SHADOW_TOGGLE_KEY) {
+        shadow.0 = !shadow.0;
+    }
+}
+
+fn update_ball_shadow(
+    shadow: Res<BallShadow>,
+    style: Res<BallShadowStyle>,
+    ball_query: Query<&Transform, (With<Ball>, Without<BallShadowOf>)>,
+    mut shadow_query: Query<(&BallShadowOf, &mut Transform, &mut Visibility, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (shadow_of, mut transform, mut visibility, material) in &mut shadow_query {
+        *visibility = if shadow.0 {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+
+        if let Ok(ball_transform) = ball_query.get(shadow_of.0) {
+            transform.translation =
+                (ball_transform.translation.truncate() + style.offset).extend(BALL_SHADOW_Z);
+        }
+
+        if style.is_changed() {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = Color::BLACK.with_a(style.opacity);
+            }
+        }
+    }
+}
+
+fn toggle_grid(keyboard_input: Res<ButtonInput<KeyCode>>, mut show_grid: ResMut<ShowGrid>) {
+    if keyboard_input.just_pressed(GRID_TOGGLE_KEY) {
+        show_grid.0 = !show_grid.0;
+    }
+}
+
+fn update_grid_visibility(
+    show_grid: Res<ShowGrid>,
+    mut query: Query<&mut Visibility, With<GridLine>>,
+) {
+    if !show_grid.is_changed() {
+        return;
+    }
+
+    for mut visibility in &mut query {
+        *visibility = if show_grid.0 {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn toggle_wall_glow(keyboard_input: Res<ButtonInput<KeyCode>>, mut glow: ResMut<WallGlow>) {
+    if keyboard_input.just_pressed(WALL_GLOW_TOGGLE_KEY) {
+        glow.enabled = !glow.enabled;
+    }
+}
+
+fn update_wall_glow(
+    glow: Res<WallGlow>,
+    config: Res<GameConfig>,
+    ball_query: Query<&Transform, With<Ball>>,
+    mut wall_query: Query<(&WallLocation, &mut Sprite)>,
+) {
+    let ball = ball_query.single().translation;
+
+    for (location, mut sprite) in &mut wall_query {
+        if !glow.enabled {
+            sprite.color = config.wall_color;
+            continue;
+        }
+
+        let (distance, range) = match location {
+            WallLocation::Left => (ball.x - config.left_wall(), config.arena_w / 2.),
+            WallLocation::Right => (config.right_wall() - ball.x, config.arena_w / 2.),
+            WallLocation::Bottom => (ball.y - config.bottom_wall(), config.arena_h / 2.),
+            WallLocation::Top => (config.top_wall() - ball.y, config.arena_h / 2.),
+        };
+        let proximity = 1.0 - (distance / range).clamp(0.0, 1.0);
+
+        // squaring keeps the glow subtle until the ball is actually close
+        sprite.color = lerp_color(config.wall_color, WALL_GLOW_COLOR, proximity * proximity * glow.intensity);
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let [r1, g1, b1, a1] = from.as_rgba_f32();
+    let [r2, g2, b2, a2] = to.as_rgba_f32();
+
+    Color::rgba(
+        r1 + (r2 - r1) * t,
+        g1 + (g2 - g1) * t,
+        b1 + (b2 - b1) * t,
+        a1 + (a2 - a1) * t,
+    )
+}
+
+fn update_bullet_time(
+    mut bullet_time: ResMut<BulletTime>,
+    mut time_scale: ResMut<TimeScale>,
+    time: Res<Time<Real>>,
+) {
+    if !bullet_time.active {
+        return;
+    }
+
+    bullet_time.timer.tick(time.delta());
+    if bullet_time.timer.finished() {
+        bullet_time.active = false;
+        time_scale.0 = 1.0;
+    } else {
+        time_scale.0 = bullet_time.slowdown;
+    }
+}
+
+fn toggle_consistent_serve(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut consistent_serve: ResMut<ConsistentServe>,
+) {
+    if keyboard_input.just_pressed(CONSISTENT_SERVE_TOGGLE_KEY) {
+        consistent_serve.0 = !consistent_serve.0;
+    }
+}
+
+fn toggle_comeback_assist(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut comeback_assist: ResMut<ComebackAssist>,
+) {
+    if keyboard_input.just_pressed(COMEBACK_ASSIST_TOGGLE_KEY) {
+        comeback_assist.0 = !comeback_assist.0;
+    }
+}
+
+fn toggle_score_hit_stop(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut score_hit_stop: ResMut<ScoreHitStop>,
+) {
+    if keyboard_input.just_pressed(SCORE_HIT_STOP_TOGGLE_KEY) {
+        score_hit_stop.0 = !score_hit_stop.0;
+    }
+}
+
+/// Runs after `update_bullet_time` so the freeze wins over any slowdown, and
+/// hands the time scale back to it once done
+fn update_hit_stop(
+    mut hit_stop: ResMut<HitStop>,
+    bullet_time: Res<BulletTime>,
+    mut time_scale: ResMut<TimeScale>,
+    time: Res<Time<Real>>,
+) {
+    if !hit_stop.active {
+        return;
+    }
+
+    hit_stop.timer.tick(time.delta());
+    if hit_stop.timer.finished() {
+        hit_stop.active = false;
+        time_scale.0 = if bullet_time.active { bullet_time.slowdown } else { 1.0 };
+    } else {
+        time_scale.0 = 0.0;
+    }
+}
+
+fn apply_time_scale(time_scale: Res<TimeScale>, mut time: ResMut<Time<Virtual>>) {
+    if time_scale.is_changed() {
+        time.set_relative_speed(time_scale.0);
+    }
+}
+
+fn toggle_paddle_ghosting(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut ghosting: ResMut<PaddleGhosting>,
+) {
+    if keyboard_input.just_pressed(PADDLE_GHOST_TOGGLE_KEY) {
+        ghosting.0 = !ghosting.0;
+    }
+}
+
+fn fade_paddle_ghosts(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut PaddleGhost, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut ghost, mut sprite) in &mut query {
+        ghost.tick(time.delta());
+
+        if ghost.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(PADDLE_GHOST_ALPHA * ghost.fraction_remaining());
+        }
+    }
+}
+
+fn toggle_ball_wobble(keyboard_input: Res<ButtonInput<KeyCode>>, mut wobble: ResMut<BallWobble>) {
+    if keyboard_input.just_pressed(BALL_WOBBLE_TOGGLE_KEY) {
+        wobble.enabled = !wobble.enabled;
+    }
+}
+
+fn wobble_ball(
+    wobble: Res<BallWobble>,
+    mut query: Query<(&mut Transform, &mut WobblePhase, &Velocity), With<Ball>>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut phase, velocity) in &mut query {
+        if !wobble.enabled {
+            transform.scale = Vec3::ONE;
+            continue;
+        }
+
+        let speed = to_render(velocity.0).length();
+        phase.0 = (phase.0 + speed * BALL_WOBBLE_FREQUENCY * time.delta_seconds()).rem_euclid(TAU);
+
+        let strength = ((speed - BALL_WOBBLE_MIN_SPEED)
+            / (BALL_WOBBLE_MAX_SPEED - BALL_WOBBLE_MIN_SPEED))
+            .clamp(0.0, 1.0);
+        let offset = wobble.amplitude * strength * phase.0.sin();
+
+        // squash one axis while stretching the other so the ball keeps its volume
+        transform.scale = Vec3::new(1.0 + offset, 1.0 - offset, 1.0);
+    }
+}
+
+fn record_frame_time(mut frame_graph: ResMut<FrameGraph>, time: Res<Time<Real>>) {
+    // keep the buffer bounded, dropping the oldest frame first
+    if frame_graph.deltas.len() == FRAME_GRAPH_CAPACITY {
+        frame_graph.deltas.pop_front();
+    }
+    frame_graph.deltas.push_back(time.delta_seconds());
+}
+
+fn toggle_frame_graph(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut frame_graph: ResMut<FrameGraph>,
+) {
+    if keyboard_input.just_pressed(FRAME_GRAPH_TOGGLE_KEY) {
+        frame_graph.visible = !frame_graph.visible;
+    }
+}
+
+fn draw_frame_graph(
+    frame_graph: Res<FrameGraph>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    mut gizmos: Gizmos,
+) {
+    if !frame_graph.visible {
+        return;
+    }
+
+    let window = window_query.single();
+    let camera = camera_query.single();
+    // the graph is laid out in screen space, gizmos are drawn in world space
+    let to_world = |point: Vec2| camera.transform_point(point.extend(0.)).truncate();
+
+    let origin = Vec2::new(
+        -window.width() / 2. + FRAME_GRAPH_MARGIN,
+        -window.height() / 2. + FRAME_GRAPH_MARGIN,
+    );
+    let width = FRAME_GRAPH_CAPACITY as f32 * FRAME_GRAPH_BAR_W;
+
+    for (i, delta) in frame_graph.deltas.iter().enumerate() {
+        let x = origin.x + i as f32 * FRAME_GRAPH_BAR_W;
+        let height = (delta / FRAME_GRAPH_MAX_DELTA).min(1.0) * FRAME_GRAPH_H;
+        let color = if *delta <= FRAME_GRAPH_TARGET_DELTA * 1.1 {
+            Color::GREEN
+        } else if *delta < FRAME_GRAPH_MAX_DELTA {
+            Color::YELLOW
+        } else {
+            Color::RED
+        };
+
+        gizmos.line_2d(
+            to_world(Vec2::new(x, origin.y)),
+            to_world(Vec2::new(x, origin.y + height)),
+            color,
+        );
+    }
+
+    // reference line at the 60 fps frame time
+    let target_y = origin.y + FRAME_GRAPH_TARGET_DELTA / FRAME_GRAPH_MAX_DELTA * FRAME_GRAPH_H;
+    gizmos.line_2d(
+        to_world(Vec2::new(origin.x, target_y)),
+        to_world(Vec2::new(origin.x + width, target_y)),
+        Color::GRAY,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::math::DVec2;
+
+    use super::*;
+
+    const TICK: f32 = 1.0 / 64.0;
+
+    /// An app running `systems` on `FixedUpdate`, ticked by hand with `tick`,
+    /// with the default resources the scoring systems need
+    fn test_app<M>(systems: impl IntoSystemConfigs<M>) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<CollisionEvent>()
+            .init_resource::<GameConfig>()
+            .init_resource::<BulletTime>()
+            .init_resource::<Score>()
+            .init_resource::<GoalLine>()
+            .init_resource::<PendingServe>()
+            .init_resource::<ConsistentServe>()
+            .init_resource::<MinServeAngle>()
+            .init_resource::<AssistMode>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<RallyStats>()
+            .init_resource::<MaxRallyDuration>()
+            .init_resource::<ScoreHitStop>()
+            .init_resource::<HitStop>()
+            .add_systems(FixedUpdate, systems);
+
+        app
+    }
+
+    fn tick(app: &mut App) {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(TICK));
+        app.world.run_schedule(FixedUpdate);
+    }
+
+    fn spawn_ball(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+        app.world.spawn((
+            Ball,
+            Transform::from_translation(position.extend(BALL_Z)),
+            Position(to_phys(position)),
+            Velocity(to_phys(velocity)),
+            LastCollider::default(),
+        )).id()
+    }
+
+    fn spawn_paddle(app: &mut App, position: Vec2) -> Entity {
+        app.world.spawn((
+            Paddle,
+            Collider,
+            Transform {
+                translation: position.extend(PADDLE_Z),
+                scale: Vec3::new(PADDLE_W, PADDLE_H, 1.0),
+                ..default()
+            },
+        )).id()
+    }
+
+    fn velocity(app: &App, ball: Entity) -> Vec2 {
+        to_render(app.world.get::<Velocity>(ball).unwrap().0)
+    }
+
+    fn collision_events(app: &App) -> usize {
+        app.world.resource::<Events<CollisionEvent>>().len()
+    }
+
+    /// Ticks `ticks` times while moving `paddle` along x at `paddle_speed`,
+    /// counting how often the ball's x velocity changes sign
+    fn count_x_flips(app: &mut App, ball: Entity, paddle: Entity, paddle_speed: f32, ticks: usize) -> usize {
+        let mut flips = 0;
+        let mut last_x = velocity(app, ball).x;
+        for _ in 0..ticks {
+            app.world.get_mut::<Transform>(paddle).unwrap().translation.x += paddle_speed * TICK;
+            tick(app);
+
+            let x = velocity(app, ball).x;
+            if x.signum() != last_x.signum() {
+                flips += 1;
+            }
+            last_x = x;
+        }
+
+        flips
+    }
+
+    #[test]
+    fn ball_pushed_back_into_paddle_reflects_once() {
+        let mut app = test_app((apply_velocity, check_for_collision).chain());
+        app.world.resource_mut::<GameConfig>().ball_speed = 20.0;
+        // no `HitCooldown`, so only `LastCollider` keeps the paddle from
+        // hitting the ball a second time
+        let paddle = spawn_paddle(&mut app, Vec2::ZERO);
+        let ball = spawn_ball(&mut app, Vec2::new(PADDLE_W / 2. + 25.0, 0.0), Vec2::new(-20.0, 0.0));
+
+        // the paddle runs past the ball's center within `BALL_COLLISION_DEBOUNCE`,
+        // so the ball ends up against its other face still moving into it
+        let flips = count_x_flips(&mut app, ball, paddle, 3.0 * PADDLE_SPEED, 10);
+
+        assert_eq!(flips, 1);
+        assert_eq!(collision_events(&app), 1);
+    }
+
+    #[test]
+    fn f64_integration_drifts_less_than_f32() {
+        // neither is exact in binary, so every step rounds
+        let ticks = 100_000;
+        let velocity = DVec2::new(333.3, -123.4);
+        let dt = 1.0 / 60.0;
+
+        let mut single = Vec2::ZERO;
+        let mut double = DVec2::ZERO;
+        for _ in 0..ticks {
+            single += velocity.as_vec2() * dt as f32;
+            double += velocity * dt;
+        }
+
+        let expected = velocity * (ticks as f64 * dt);
+        let single_error = single.as_dvec2().distance(expected);
+        let double_error = double.distance(expected);
+        assert!(double_error < single_error, "f64 drifted {double_error}, f32 {single_error}");
+    }
+
+    #[cfg(feature = "high_precision")]
+    #[test]
+    fn high_precision_ball_position_drifts_less_than_f32() {
+        let mut app = test_app(apply_velocity);
+        let velocity = Vec2::new(333.3, -123.4);
+        let ball = spawn_ball(&mut app, Vec2::ZERO, velocity);
+        // what `apply_velocity` would compute without the feature
+        let mut single = Vec2::ZERO;
+
+        let ticks = 20_000;
+        for _ in 0..ticks {
+            tick(&mut app);
+            single += velocity * TICK;
+        }
+
+        let expected = velocity.as_dvec2() * (ticks as f64 * TICK as f64);
+        let position = app.world.get::<Position>(ball).unwrap().0;
+        let double_error = position.distance(expected);
+        let single_error = single.as_dvec2().distance(expected);
+        assert!(double_error < single_error, "f64 drifted {double_error}, f32 {single_error}");
+    }
+
+    fn assert_serve(direction: Vec2, min_angle: f32) -> Vec2 {
+        let clamped = clamp_serve_angle(direction, min_angle);
+
+        assert!((clamped.length() - 1.0).abs() < 1e-5, "{clamped} is not unit length");
+        assert!(clamped.y.atan2(clamped.x.abs()).abs() >= min_angle - 1e-5, "{clamped} is too flat");
+        assert_eq!(clamped.x.signum(), direction.x.signum());
+
+        clamped
+    }
+
+    #[test]
+    fn flat_serves_are_steepened() {
+        let min_angle = MIN_SERVE_ANGLE.to_radians();
+
+        assert!(assert_serve(Vec2::new(1.0, 0.0), min_angle).y > 0.0);
+        assert!(assert_serve(Vec2::new(-1.0, 0.0), min_angle).y > 0.0);
+        assert!(assert_serve(Vec2::new(1.0, -0.01), min_angle).y < 0.0);
+    }
+
+    #[test]
+    fn steep_serves_are_only_normalized() {
+        let min_angle = MIN_SERVE_ANGLE.to_radians();
+        let direction = Vec2::new(-1.0, 1.0);
+
+        let clamped = assert_serve(direction, min_angle);
+        assert!(clamped.abs_diff_eq(direction.normalize(), 1e-6));
+    }
+
+    #[test]
+    fn paddle_moving_into_slow_ball_reflects_once() {
+        let mut app = test_app((apply_velocity, check_for_collision).chain());
+        app.world.resource_mut::<GameConfig>().ball_speed = 20.0;
+        let paddle = spawn_paddle(&mut app, Vec2::ZERO);
+        app.world.entity_mut(paddle).insert(HitCooldown::default());
+        let ball = spawn_ball(&mut app, Vec2::new(PADDLE_W / 2. + 25.0, 0.0), Vec2::new(-20.0, 0.0));
+
+        // fast enough to run past the ball's center within the hit cooldown,
+        // which would bounce it back into the paddle's left face
+        let ticks = (PADDLE_HIT_COOLDOWN / TICK) as usize;
+        let flips = count_x_flips(&mut app, ball, paddle, 2.0 * PADDLE_SPEED, ticks);
+
+        assert_eq!(flips, 1);
+        assert_eq!(collision_events(&app), 1);
+    }
+
+    /// Velocity of a ball hitting the right face of a paddle `offset_y` above its center
+    fn bounce_off_paddle(shape: PaddleShape, offset_y: f32) -> Vec2 {
+        let mut app = test_app(check_for_collision);
+        app.world.resource_mut::<GameConfig>().paddle_shape = shape;
+        spawn_paddle(&mut app, Vec2::ZERO);
+        let ball = spawn_ball(&mut app, Vec2::new(PADDLE_W / 2. + 15.0, offset_y), Vec2::new(-BALL_SPEED, 0.0));
+
+        tick(&mut app);
+        velocity(&app, ball)
+    }
+
+    #[test]
+    fn paddle_hits_aim_by_hit_position() {
+        for shape in [PaddleShape::Box, PaddleShape::Capsule] {
+            let center = bounce_off_paddle(shape, 0.0);
+            let high = bounce_off_paddle(shape, PADDLE_H / 4.);
+            let low = bounce_off_paddle(shape, -PADDLE_H / 4.);
+
+            assert_eq!(center, Vec2::new(BALL_SPEED, 0.0), "{shape:?}");
+            assert!(high.x > 0.0 && high.y > 0.0, "{shape:?} sent a high hit to {high}");
+            assert!(low.x > 0.0 && low.y < 0.0, "{shape:?} sent a low hit to {low}");
+            assert!((high.length() - BALL_SPEED).abs() < 1e-3, "{shape:?}");
+        }
+    }
+
+    #[test]
+    fn wobble_phase_follows_distance_travelled() {
+        let mut app = test_app(wobble_ball);
+        app.insert_resource(BallWobble { enabled: true, ..default() });
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(BALL_WOBBLE_MAX_SPEED, 0.0));
+        app.world.entity_mut(ball).insert(WobblePhase::default());
+        for _ in 0..100 {
+            tick(&mut app);
+        }
+
+        // a sudden slowdown only slows the oscillation instead of jumping it
+        let before = app.world.get::<WobblePhase>(ball).unwrap().0;
+        let speed = BALL_WOBBLE_MAX_SPEED - 60.0;
+        app.world.get_mut::<Velocity>(ball).unwrap().0 = to_phys(Vec2::new(speed, 0.0));
+        tick(&mut app);
+
+        let after = app.world.get::<WobblePhase>(ball).unwrap().0;
+        let step = (after - before).rem_euclid(TAU);
+        assert!((step - speed * BALL_WOBBLE_FREQUENCY * TICK).abs() < 1e-4, "phase moved by {step}");
+    }
+
+    #[test]
+    fn resized_paddles_match_the_new_config() {
+        let mut app = test_app(apply_config);
+        app.init_resource::<Assets<ColorMaterial>>();
+        let old_config = GameConfig::default();
+        let top = Vec2::new(old_config.paddle_x(Player::Left), paddle_bounds(&old_config).1);
+        let paddle = spawn_paddle(&mut app, top);
+        app.world.entity_mut(paddle).insert((Sprite::default(), Player::Left));
+        tick(&mut app);
+
+        // a taller paddle at the top has to move down to stay inside the walls
+        let config = GameConfig { paddle_w: 30.0, paddle_h: 300.0, ..default() };
+        app.insert_resource(config.clone());
+        tick(&mut app);
+
+        let transform = app.world.get::<Transform>(paddle).unwrap();
+        let (_, top_bound) = paddle_bounds(&config);
+        assert_eq!(transform.scale, Vec3::new(30.0, 300.0, 1.0));
+        assert_eq!(transform.translation.x, config.paddle_x(Player::Left));
+        assert_eq!(transform.translation.y, top_bound);
+    }
+
+    fn capsule() -> PaddleCapsule {
+        PaddleCapsule::from_aabb(Aabb2d::new(Vec2::ZERO, Vec2::new(PADDLE_W, PADDLE_H) / 2.))
+    }
+
+    #[test]
+    fn capsule_cap_hit_deflects_vertically() {
+        let top = PADDLE_H / 2. - PADDLE_W / 2.;
+        let ball = BoundingCircle::new(Vec2::new(15.0, top + 10.0), 20.0);
+
+        let normal = collide_with_capsule(ball, capsule()).unwrap();
+        assert!(normal.x > 0.0 && normal.y > 0.0, "{normal}");
+    }
+
+    #[test]
+    fn capsule_cap_hit_mirrors_ball_around_normal() {
+        let mut app = test_app(check_for_collision);
+        app.world.resource_mut::<GameConfig>().paddle_shape = PaddleShape::Capsule;
+        spawn_paddle(&mut app, Vec2::ZERO);
+        let position = Vec2::new(15.0, PADDLE_H / 2. - PADDLE_W / 2. + 10.0);
+        let incoming = Vec2::new(-BALL_SPEED, 0.0);
+        let ball = spawn_ball(&mut app, position, incoming);
+
+        tick(&mut app);
+
+        let normal = collide_with_capsule(BoundingCircle::new(position, BALL_DIAMETER * 0.8), capsule()).unwrap();
+        let expected = incoming - 2.0 * incoming.dot(normal) * normal;
+        let reflected = velocity(&app, ball);
+        assert!(reflected.abs_diff_eq(expected, 1e-3), "{reflected} != {expected}");
+        assert!(reflected.x > 0.0 && reflected.y > 0.0, "{reflected}");
+        assert_eq!(collision_events(&app), 1);
+    }
+
+    #[test]
+    fn capsule_face_hit_deflects_horizontally() {
+        let right = BoundingCircle::new(Vec2::new(25.0, 0.0), 20.0);
+        let left = BoundingCircle::new(Vec2::new(-25.0, 0.0), 20.0);
+
+        assert_eq!(collide_with_capsule(right, capsule()), Some(Vec2::new(1.0, 0.0)));
+        assert_eq!(collide_with_capsule(left, capsule()), Some(Vec2::new(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn ball_just_past_the_cap_misses() {
+        // touching the corner of the paddle's box, but not its rounded end
+        let corner = Vec2::new(PADDLE_W, PADDLE_H) / 2.;
+        let ball = BoundingCircle::new(corner + Vec2::splat(12.0), 20.0);
+
+        assert!(collide_with_side(ball, Aabb2d::new(Vec2::ZERO, corner)).is_some());
+        assert_eq!(collide_with_capsule(ball, capsule()), None);
+    }
+
+    #[test]
+    fn wall_without_gaps_is_one_segment() {
+        assert_eq!(wall_segments(&[], 100.0), vec![(-100.0, 100.0)]);
+    }
+
+    #[test]
+    fn wall_gap_splits_wall() {
+        assert_eq!(wall_segments(&[[-10.0, 10.0]], 100.0), vec![(-100.0, -10.0), (10.0, 100.0)]);
+    }
+
+    #[test]
+    fn overlapping_wall_gaps_merge() {
+        assert_eq!(
+            wall_segments(&[[0.0, 40.0], [-30.0, 10.0]], 100.0),
+            vec![(-100.0, -30.0), (40.0, 100.0)],
+        );
+    }
+
+    #[test]
+    fn wall_gaps_are_clamped_to_the_wall() {
+        assert_eq!(wall_segments(&[[80.0, 150.0]], 100.0), vec![(-100.0, 80.0)]);
+        assert_eq!(wall_segments(&[[-150.0, 100.0]], 100.0), vec![]);
+    }
+
+    #[test]
+    fn reversed_wall_gap_is_accepted() {
+        assert_eq!(wall_segments(&[[10.0, -10.0]], 100.0), vec![(-100.0, -10.0), (10.0, 100.0)]);
+    }
+
+    #[test]
+    fn ball_wraps_through_top_gap() {
+        let mut app = test_app((apply_velocity, check_for_collision, wrap_through_wall_gaps).chain());
+        let config = GameConfig {
+            wall_gaps: WallGaps { top: vec![[-100.0, 100.0]], bottom: vec![] },
+            ..default()
+        };
+        let half_width = (config.arena_w + config.wall_thickness) / 2.;
+        for (start, end) in wall_segments(&config.wall_gaps.top, half_width) {
+            app.world.spawn(WallBundle::segment(WallLocation::Top, start, end, &config));
+        }
+        app.world.spawn(WallBundle::new(WallLocation::Bottom, &config));
+        app.insert_resource(config.clone());
+
+        let ball_velocity = Vec2::new(0.0, BALL_SPEED);
+        let ball = spawn_ball(&mut app, Vec2::new(0.0, config.top_wall() - 30.0), ball_velocity);
+
+        for _ in 0..20 {
+            tick(&mut app);
+
+            let y = app.world.get::<Transform>(ball).unwrap().translation.y;
+            if y < 0.0 {
+                assert_eq!(y, config.bottom_wall() + config.wall_thickness + BALL_DIAMETER);
+                assert_eq!(to_render(app.world.get::<Position>(ball).unwrap().0).y, y);
+                assert_eq!(velocity(&app, ball), ball_velocity);
+                assert_eq!(collision_events(&app), 0);
+                return;
+            }
+        }
+
+        panic!("the ball never wrapped around");
+    }
+
+    #[test]
+    fn scoring_triggers_past_the_goal_line() {
+        let mut app = test_app(check_for_score);
+        app.insert_resource(GoalLine { left: -550.0, right: 620.0 });
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::ZERO);
+
+        let score_at = |app: &mut App, x: f32| {
+            app.world.get_mut::<Transform>(ball).unwrap().translation.x = x;
+            tick(app);
+            let score = app.world.resource::<Score>();
+            (score.left, score.right)
+        };
+
+        assert_eq!(score_at(&mut app, -550.0), (0, 0));
+        assert_eq!(score_at(&mut app, -550.01), (0, 1));
+        assert_eq!(score_at(&mut app, 620.0), (0, 1));
+        assert_eq!(score_at(&mut app, 620.01), (1, 1));
+    }
+
+    #[test]
+    fn consistent_serve_is_flat_at_ball_speed() {
+        let mut app = test_app((check_for_score, serve_ball).chain());
+        app.insert_resource(ConsistentServe(true));
+        let left_wall = app.world.resource::<GameConfig>().left_wall();
+        let ball = spawn_ball(&mut app, Vec2::new(left_wall - 1.0, 0.0), Vec2::new(-BALL_SPEED, 100.0));
+
+        tick(&mut app);
+
+        assert_eq!(velocity(&app, ball), Vec2::new(-BALL_SPEED, 0.0));
+        assert_eq!(app.world.get::<Transform>(ball).unwrap().translation.y, 0.0);
+    }
+
+    #[test]
+    fn rally_peak_speed_resets_when_a_point_ends() {
+        let mut app = test_app((check_for_score, track_rally_speed).chain());
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(500.0, 0.0));
+
+        tick(&mut app);
+        app.world.get_mut::<Velocity>(ball).unwrap().0 = to_phys(Vec2::new(300.0, 0.0));
+        tick(&mut app);
+        assert_eq!(app.world.resource::<RallyStats>().peak_speed, 500.0);
+
+        let right_wall = app.world.resource::<GameConfig>().right_wall();
+        app.world.get_mut::<Transform>(ball).unwrap().translation.x = right_wall + 1.0;
+        tick(&mut app);
+        // the serve is still pending, so the ball hasn't moved since
+        assert_eq!(app.world.resource::<RallyStats>().peak_speed, 0.0);
+    }
+
+    #[test]
+    fn speeds_are_reported_in_the_configured_unit() {
+        assert_eq!(SpeedUnit::Px.format(412.4), "412 px/s");
+        assert_eq!(SpeedUnit::Mph.format(400.0), "40 mph");
+    }
+
+    /// An app deciding rallies that may last at most one second
+    fn capped_rally_app(timeout: RallyTimeout) -> App {
+        let mut app = test_app((tick_rally_clock, check_for_score).chain());
+        let mut config = app.world.resource_mut::<GameConfig>();
+        config.max_rally_duration = 1.0;
+        config.rally_timeout = timeout;
+
+        for player in [Player::Left, Player::Right] {
+            let x = app.world.resource::<GameConfig>().paddle_x(player);
+            let paddle = spawn_paddle(&mut app, Vec2::new(x, 0.0));
+            app.world.entity_mut(paddle).insert(player);
+        }
+
+        app
+    }
+
+    /// Holds the ball still on the left half until the rally clock runs out,
+    /// returning the score and ball position right after
+    fn run_rally_past_cap(app: &mut App) -> ((u32, u32), Vec2) {
+        let ball = spawn_ball(app, Vec2::new(-100.0, 0.0), Vec2::ZERO);
+        let ticks = (1.0 / TICK) as usize;
+
+        for _ in 0..ticks - 1 {
+            tick(app);
+        }
+        assert_eq!(app.world.get::<Transform>(ball).unwrap().translation.x, -100.0);
+
+        tick(app);
+        let score = app.world.resource::<Score>();
+        let position = app.world.get::<Transform>(ball).unwrap().translation.truncate();
+        ((score.left, score.right), position)
+    }
+
+    #[test]
+    fn rally_past_the_cap_is_served_again() {
+        let mut app = capped_rally_app(RallyTimeout::Reserve);
+
+        let (score, position) = run_rally_past_cap(&mut app);
+
+        assert_eq!(score, (0, 0));
+        assert_eq!(position, Vec2::new(BALL_INITIAL_POSITION.x, 0.0));
+        assert!(app.world.resource::<PendingServe>().0.is_some());
+    }
+
+    #[test]
+    fn rally_past_the_cap_goes_to_the_closest_paddle() {
+        let mut app = capped_rally_app(RallyTimeout::Award);
+
+        let (score, position) = run_rally_past_cap(&mut app);
+
+        assert_eq!(score, (1, 0));
+        assert_eq!(position, Vec2::new(BALL_INITIAL_POSITION.x, 0.0));
+    }
+
+    #[test]
+    fn boost_is_used_up_until_the_next_serve() {
+        let mut app = test_app((boost_ball, check_for_score).chain());
+        app.world.resource_mut::<GameConfig>().ball_boost = true;
+        let mut keyboard_input = ButtonInput::<KeyCode>::default();
+        keyboard_input.press(LEFT_BOOST_KEY);
+        app.insert_resource(keyboard_input);
+        let paddle = spawn_paddle(&mut app, Vec2::new(-500.0, 0.0));
+        app.world.entity_mut(paddle).insert((Player::Left, BoostCharge(true)));
+        let ball = spawn_ball(&mut app, Vec2::new(-100.0, 0.0), Vec2::new(-BALL_SPEED, 0.0));
+        let boosted = BALL_SPEED * BALL_BOOST_FACTOR;
+
+        tick(&mut app);
+        assert_eq!(velocity(&app, ball).length(), boosted);
+
+        // holding the key any longer does nothing
+        tick(&mut app);
+        assert_eq!(velocity(&app, ball).length(), boosted);
+        assert!(!app.world.get::<BoostCharge>(paddle).unwrap().0);
+
+        let left_wall = app.world.resource::<GameConfig>().left_wall();
+        app.world.get_mut::<Transform>(ball).unwrap().translation.x = left_wall - 1.0;
+        tick(&mut app);
+        assert!(app.world.get::<BoostCharge>(paddle).unwrap().0);
+    }
+
+    #[test]
+    fn boost_is_capped_at_max_ball_speed() {
+        let mut app = test_app(boost_ball);
+        app.world.resource_mut::<GameConfig>().ball_boost = true;
+        let mut keyboard_input = ButtonInput::<KeyCode>::default();
+        keyboard_input.press(RIGHT_BOOST_KEY);
+        app.insert_resource(keyboard_input);
+        let paddle = spawn_paddle(&mut app, Vec2::new(500.0, 0.0));
+        app.world.entity_mut(paddle).insert((Player::Right, BoostCharge(true)));
+        let ball = spawn_ball(&mut app, Vec2::new(100.0, 0.0), Vec2::new(MAX_BALL_SPEED * 0.9, 0.0));
+
+        tick(&mut app);
+
+        assert!((velocity(&app, ball).length() - MAX_BALL_SPEED).abs() < 1e-3);
+    }
+
+    #[test]
+    fn serve_waits_for_the_server_to_reach_the_serve_zone() {
+        let mut app = test_app(serve_ball);
+        app.world.resource_mut::<GameConfig>().serve_zone = 100.0;
+        let server = spawn_paddle(&mut app, Vec2::new(-500.0, 300.0));
+        app.world.entity_mut(server).insert(Player::Left);
+        // the receiver being out of the zone does not matter
+        let receiver = spawn_paddle(&mut app, Vec2::new(500.0, 300.0));
+        app.world.entity_mut(receiver).insert(Player::Right);
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::ZERO);
+        app.world.resource_mut::<PendingServe>().0 = Some(1.0);
+
+        tick(&mut app);
+        assert_eq!(velocity(&app, ball), Vec2::ZERO);
+        assert!(app.world.resource::<PendingServe>().0.is_some());
+
+        app.world.get_mut::<Transform>(server).unwrap().translation.y = -40.0;
+        tick(&mut app);
+        assert!(velocity(&app, ball).x > 0.0);
+        assert!(app.world.resource::<PendingServe>().0.is_none());
+    }
+
+    #[test]
+    fn assisted_serve_waits_for_a_click() {
+        let mut app = test_app(serve_ball);
+        app.world.resource_mut::<AssistMode>().0 = Some(Player::Left);
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::ZERO);
+
+        // the other player's serve goes right away
+        app.world.resource_mut::<PendingServe>().0 = Some(-1.0);
+        tick(&mut app);
+        assert!(velocity(&app, ball).x < 0.0);
+
+        app.world.resource_mut::<PendingServe>().0 = Some(1.0);
+        tick(&mut app);
+        assert!(app.world.resource::<PendingServe>().0.is_some());
+
+        app.world.resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Right);
+        tick(&mut app);
+        assert!(velocity(&app, ball).x > 0.0);
+        assert!(app.world.resource::<PendingServe>().0.is_none());
+    }
+
+    #[test]
+    fn winning_point_starts_a_new_match() {
+        let mut app = test_app(check_for_score);
+        app.world.resource_mut::<GameConfig>().win_score = 2;
+        app.insert_resource(Score { left: 1, right: 0 });
+        let right_wall = app.world.resource::<GameConfig>().right_wall();
+        spawn_ball(&mut app, Vec2::new(right_wall + 1.0, 0.0), Vec2::ZERO);
+
+        tick(&mut app);
+
+        let score = app.world.resource::<Score>();
+        assert_eq!((score.left, score.right), (0, 0));
+    }
+
+    #[test]
+    fn score_hit_stop_freezes_time_briefly() {
+        let mut app = test_app(check_for_score);
+        app.insert_resource(ScoreHitStop(true))
+            .init_resource::<TimeScale>()
+            .add_systems(Update, update_hit_stop);
+        let right_wall = app.world.resource::<GameConfig>().right_wall();
+        spawn_ball(&mut app, Vec2::new(right_wall + 1.0, 0.0), Vec2::ZERO);
+
+        tick(&mut app);
+        assert!(app.world.resource::<HitStop>().active);
+
+        let time_scale_after = |app: &mut App, seconds: f32| {
+            app.world.resource_mut::<Time<Real>>().advance_by(Duration::from_secs_f32(seconds));
+            app.world.run_schedule(Update);
+            app.world.resource::<TimeScale>().0
+        };
+        assert_eq!(time_scale_after(&mut app, SCORE_HIT_STOP_DURATION / 2.), 0.0);
+        assert_eq!(time_scale_after(&mut app, SCORE_HIT_STOP_DURATION), 1.0);
+    }
+
+    #[test]
+    fn comeback_assist_slows_ball_toward_trailing_player() {
+        let mut app = test_app(apply_comeback_assist);
+        app.insert_resource(ComebackAssist(true))
+            .insert_resource(Score { left: 0, right: 10 });
+        let toward_left = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(-BALL_SPEED, 0.0));
+        let toward_right = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(BALL_SPEED, 0.0));
+
+        tick(&mut app);
+
+        let slowed = velocity(&app, toward_left).length();
+        assert!(slowed < BALL_SPEED * 0.95, "{slowed} is barely slower");
+        assert!((slowed - BALL_SPEED * (1.0 - COMEBACK_MAX_SLOWDOWN)).abs() < 1e-3);
+        assert_eq!(velocity(&app, toward_right).length(), BALL_SPEED);
+    }
 }